@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    VerifyStarted {
+        component: String,
+        total_bytes: Option<u64>,
+    },
+    VerifyFinished {
+        component: String,
+        queued: usize,
+    },
+    QueueStarted {
+        files: usize,
+        total_bytes: u64,
+    },
+    TaskStarted {
+        url: String,
+        path: PathBuf,
+        total_bytes: Option<u64>,
+    },
+    BytesReceived {
+        url: String,
+        received: u64,
+        total_bytes: Option<u64>,
+    },
+    TaskRetrying {
+        url: String,
+        attempt: u32,
+        error: String,
+    },
+    TaskFinished {
+        url: String,
+        path: PathBuf,
+    },
+    TaskFailed {
+        url: String,
+        path: PathBuf,
+        error: String,
+    },
+    QueueFinished {
+        completed: usize,
+        failed: usize,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadProgress {
+    subscribers: Mutex<Vec<UnboundedSender<DownloadEvent>>>,
+}
+
+impl DownloadProgress {
+    pub fn new() -> DownloadProgress {
+        DownloadProgress {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> UnboundedReceiver<DownloadEvent> {
+        let (sender, receiver) = unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn emit(&self, event: DownloadEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(event.to_owned()).is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::{AcquireError, Semaphore};
+
+use crate::common::download_progress::{DownloadEvent, DownloadProgress};
+use crate::common::file_downloader;
+use crate::common::error::LauncherError;
+use crate::common::file_downloader::VerifyMode;
+use crate::common::retry_policy::RetryPolicy;
+
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 16;
+const DEFAULT_MAX_DOWNLOADS_PER_HOST: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct DownloadTask {
+    pub url: String,
+    pub path: PathBuf,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl DownloadTask {
+    pub fn new(url: &str, path: PathBuf) -> DownloadTask {
+        DownloadTask {
+            url: url.to_string(),
+            path,
+            sha1: None,
+            size: None,
+        }
+    }
+
+    pub fn with_sha1(mut self, sha1: &str) -> DownloadTask {
+        self.sha1 = Some(sha1.to_string());
+        self
+    }
+
+    pub fn with_size(mut self, size: u64) -> DownloadTask {
+        self.size = Some(size);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FailedDownload {
+    pub url: String,
+    pub path: PathBuf,
+    pub error: LauncherError,
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    pub completed: usize,
+    pub failed: Vec<FailedDownload>,
+}
+
+impl DownloadReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for DownloadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} files downloaded, {} failed", self.completed, self.failed.len())?;
+        for failed in &self.failed {
+            write!(f, "\n  {} -> {}: {}", failed.url, failed.path.display(), failed.error)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct DownloadScheduler {
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    progress: DownloadProgress,
+    max_per_host: usize,
+    verify_mode: VerifyMode,
+    permits: Arc<Semaphore>,
+    host_permits: Mutex<HashMap<String, Arc<Semaphore>>>,
+    queue: Mutex<Vec<DownloadTask>>,
+}
+
+impl DownloadScheduler {
+    pub fn new() -> DownloadScheduler {
+        DownloadScheduler {
+            client: reqwest::Client::new(),
+            retry_policy: RetryPolicy::new(),
+            progress: DownloadProgress::new(),
+            max_per_host: DEFAULT_MAX_DOWNLOADS_PER_HOST,
+            verify_mode: VerifyMode::Sha1,
+            permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS)),
+            host_permits: Mutex::new(HashMap::new()),
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> DownloadScheduler {
+        self.client = client;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> DownloadScheduler {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> DownloadScheduler {
+        self.permits = Arc::new(Semaphore::new(max_concurrent.max(1)));
+        self
+    }
+
+    pub fn with_max_per_host(mut self, max_per_host: usize) -> DownloadScheduler {
+        self.max_per_host = max_per_host.max(1);
+        self
+    }
+
+    pub fn with_verify_mode(mut self, verify_mode: VerifyMode) -> DownloadScheduler {
+        self.verify_mode = verify_mode;
+        self
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn verify_mode(&self) -> VerifyMode {
+        self.verify_mode
+    }
+
+    pub fn subscribe(&self) -> UnboundedReceiver<DownloadEvent> {
+        self.progress.subscribe()
+    }
+
+    pub fn emit(&self, event: DownloadEvent) {
+        self.progress.emit(event);
+    }
+
+    // Tasks without a checksum (maven libraries of some loaders) can only be checked by size, or
+    // by existence when the size is unknown too.
+    pub fn is_valid(&self, task: &DownloadTask) -> bool {
+        match (&task.sha1, task.size) {
+            (Some(sha1), Some(size)) => file_downloader::verify_file(&task.path, size, sha1, self.verify_mode),
+            (Some(sha1), None) => match self.verify_mode {
+                VerifyMode::Sha1 => file_downloader::sha1_file(&task.path).map(|actual| actual.eq_ignore_ascii_case(sha1)).unwrap_or(false),
+                VerifyMode::Size => task.path.is_file(),
+            },
+            (None, Some(size)) => task.path.metadata().map(|metadata| metadata.len() == size).unwrap_or(false),
+            (None, None) => task.path.is_file(),
+        }
+    }
+
+    // Queues a task unless one for the same path already is. Returns whether it was queued.
+    pub fn submit(&self, task: DownloadTask) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.iter().any(|queued| queued.path.eq(&task.path)) {
+            return false;
+        }
+        queue.push(task);
+        true
+    }
+
+    pub fn submit_invalid(&self, component: &str, tasks: Vec<DownloadTask>) -> usize {
+        self.emit(DownloadEvent::VerifyStarted {
+            component: component.to_string(),
+            total_bytes: Some(tasks.iter().filter_map(|task| task.size).sum()),
+        });
+        let mut queued: usize = 0;
+        for task in tasks {
+            if !self.is_valid(&task) && self.submit(task) {
+                queued += 1;
+            }
+        }
+        self.emit(DownloadEvent::VerifyFinished {
+            component: component.to_string(),
+            queued,
+        });
+        queued
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    pub async fn download(&self, task: DownloadTask) -> Result<(), LauncherError> {
+        // The host slot comes first: a task waiting for a busy host must not hold a global slot that
+        // a download from another host could use.
+        let host_permits = self.host_permits(&task.url);
+        let _host_permit = host_permits.acquire().await.map_err(scheduler_closed)?;
+        let _permit = self.permits.acquire().await.map_err(scheduler_closed)?;
+        file_downloader::from_url(&self.client, &task, &self.retry_policy, &self.progress).await
+    }
+
+    // Counts against the same global and per-host limits as a download.
+    pub async fn content_length(&self, url: &str) -> Result<Option<u64>, LauncherError> {
+        let host_permits = self.host_permits(url);
+        let _host_permit = host_permits.acquire().await.map_err(scheduler_closed)?;
+        let _permit = self.permits.acquire().await.map_err(scheduler_closed)?;
+        file_downloader::content_length(&self.client, url, &self.retry_policy).await
+    }
+
+    pub async fn run(&self) -> DownloadReport {
+        let tasks: Vec<DownloadTask> = std::mem::take(&mut *self.queue.lock().unwrap());
+        self.emit(DownloadEvent::QueueStarted {
+            files: tasks.len(),
+            total_bytes: tasks.iter().filter_map(|task| task.size).sum(),
+        });
+        // Every task is polled at once, the permits decide which ones transfer.
+        let results = futures::future::join_all(tasks.into_iter().map(|task| async move {
+            let url = task.url.to_owned();
+            let path = task.path.to_owned();
+            match self.download(task).await {
+                Ok(()) => None,
+                Err(error) => Some(FailedDownload { url, path, error }),
+            }
+        }))
+        .await;
+
+        let mut report = DownloadReport::default();
+        for result in results {
+            match result {
+                Some(failed) => report.failed.push(failed),
+                None => report.completed += 1,
+            }
+        }
+        self.emit(DownloadEvent::QueueFinished {
+            completed: report.completed,
+            failed: report.failed.len(),
+        });
+        report
+    }
+
+    fn host_permits(&self, url: &str) -> Arc<Semaphore> {
+        let host = match reqwest::Url::parse(url) {
+            Ok(url) => url.host_str().unwrap_or_default().to_string(),
+            Err(_) => String::new(),
+        };
+        let mut host_permits = self.host_permits.lock().unwrap();
+        host_permits.entry(host).or_insert_with(|| Arc::new(Semaphore::new(self.max_per_host))).clone()
+    }
+}
+
+fn scheduler_closed(_: AcquireError) -> LauncherError {
+    LauncherError::Io(std::io::Error::other("the download scheduler was shut down"))
+}
+
+impl Default for DownloadScheduler {
+    fn default() -> Self {
+        DownloadScheduler::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_dir, TestServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn a_busy_host_does_not_hold_global_slots() {
+        let routes: Vec<(String, Vec<u8>)> = (0..8).map(|index| (format!("/file{}", index), b"data".to_vec())).collect();
+        let server = TestServer::start_with_delay(routes.iter().map(|(path, body)| (path.as_str(), body.to_owned())).collect(), Duration::from_millis(300));
+        let root = temp_dir("scheduler");
+        let scheduler = DownloadScheduler::new().with_max_concurrent(4).with_max_per_host(2);
+        // The busy host is queued first, so its waiting tasks would take every global slot.
+        for index in 0..6 {
+            scheduler.submit(DownloadTask::new(&server.url(&format!("/file{}", index)), root.join(format!("file{}", index))));
+        }
+        for index in 6..8 {
+            scheduler.submit(DownloadTask::new(&server.localhost_url(&format!("/file{}", index)), root.join(format!("file{}", index))));
+        }
+        assert!(scheduler.run().await.is_success());
+        let first: Vec<String> = server.requests().into_iter().take(4).map(|request| request.host).collect();
+        assert_eq!(first.iter().filter(|host| host.as_str() == "localhost").count(), 2);
+        assert_eq!(server.max_active_for("127.0.0.1"), 2);
+        assert_eq!(server.max_active_for("localhost"), 2);
+        assert_eq!(server.max_active(), 4);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn global_limit_applies_across_hosts() {
+        let routes: Vec<(String, Vec<u8>)> = (0..6).map(|index| (format!("/file{}", index), b"data".to_vec())).collect();
+        let server = TestServer::start_with_delay(routes.iter().map(|(path, body)| (path.as_str(), body.to_owned())).collect(), Duration::from_millis(200));
+        let root = temp_dir("scheduler");
+        let scheduler = DownloadScheduler::new().with_max_concurrent(3).with_max_per_host(8);
+        for index in 0..6 {
+            let url = if index % 2 == 0 { server.url(&format!("/file{}", index)) } else { server.localhost_url(&format!("/file{}", index)) };
+            scheduler.submit(DownloadTask::new(&url, root.join(format!("file{}", index))));
+        }
+        assert!(scheduler.run().await.is_success());
+        assert_eq!(server.max_active(), 3);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn tasks_for_the_same_path_are_queued_once() {
+        let server = TestServer::start(Vec::from([("/file", b"data".to_vec())]));
+        let root = temp_dir("scheduler");
+        let scheduler = DownloadScheduler::new();
+        assert!(scheduler.submit(DownloadTask::new(&server.url("/file"), root.join("file"))));
+        assert!(!scheduler.submit(DownloadTask::new(&server.url("/file"), root.join("file"))));
+        assert_eq!(scheduler.queued(), 1);
+        assert!(scheduler.run().await.is_success());
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(scheduler.queued(), 0);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::common::download_scheduler::DownloadReport;
+
+#[derive(Debug)]
+pub enum LauncherError {
+    Network(reqwest::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Archive(zip::result::ZipError),
+    HashMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    Download(DownloadReport),
+    VersionNotFound(String),
+    InvalidLibrary(String),
+    InstanceNotFound(String),
+    InvalidInstance(String),
+    Auth(String),
+    Launch(String),
+    Template(String),
+    Installer(String),
+    MissingFile(PathBuf),
+}
+
+impl fmt::Display for LauncherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LauncherError::Network(e) => write!(f, "Network error: {}", e),
+            LauncherError::Io(e) => write!(f, "IO error: {}", e),
+            LauncherError::Json(e) => write!(f, "JSON error: {}", e),
+            LauncherError::Archive(e) => write!(f, "Archive error: {}", e),
+            LauncherError::HashMismatch { url, expected, actual } => write!(f, "SHA-1 mismatch for {}: expected {}, got {}", url, expected, actual),
+            LauncherError::Download(report) => write!(f, "Download failed: {}", report),
+            LauncherError::VersionNotFound(version) => write!(f, "Version {} not found", version),
+            LauncherError::InvalidLibrary(library) => write!(f, "Invalid library {}", library),
+            LauncherError::InstanceNotFound(instance) => write!(f, "Instance {} not found", instance),
+            LauncherError::InvalidInstance(message) => write!(f, "Invalid instance: {}", message),
+            LauncherError::Auth(message) => write!(f, "Authentication failed: {}", message),
+            LauncherError::Launch(message) => write!(f, "Launch failed: {}", message),
+            LauncherError::Template(message) => write!(f, "Invalid argument template: {}", message),
+            LauncherError::Installer(message) => write!(f, "Installer failed: {}", message),
+            LauncherError::MissingFile(path) => write!(f, "{} is missing and has no download", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for LauncherError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LauncherError::Network(e) => Some(e),
+            LauncherError::Io(e) => Some(e),
+            LauncherError::Json(e) => Some(e),
+            LauncherError::Archive(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for LauncherError {
+    fn from(e: reqwest::Error) -> Self {
+        LauncherError::Network(e)
+    }
+}
+
+impl From<std::io::Error> for LauncherError {
+    fn from(e: std::io::Error) -> Self {
+        LauncherError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for LauncherError {
+    fn from(e: serde_json::Error) -> Self {
+        LauncherError::Json(e)
+    }
+}
+
+impl From<zip::result::ZipError> for LauncherError {
+    fn from(e: zip::result::ZipError) -> Self {
+        LauncherError::Archive(e)
+    }
+}
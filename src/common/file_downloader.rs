@@ -1,23 +1,287 @@
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub async fn from_url(url: &str, path: &PathBuf) -> Result<(), reqwest::Error> {
-    let path_str: String = path.to_owned().into_os_string().into_string().unwrap();
-    let response = reqwest::get(url).await?;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use sha1::{Digest, Sha1};
 
-    println!("Downloading {url} to {path}", url = url, path = path_str);
-    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+use crate::common::download_progress::{DownloadEvent, DownloadProgress};
+use crate::common::download_scheduler::DownloadTask;
+use crate::common::error::LauncherError;
+use crate::common::retry_policy::RetryPolicy;
 
-    let mut file = match File::create(path) {
-        Err(why) => panic!("Couldn't Create {}", why),
-        Ok(file) => file,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    Sha1,
+    Size,
+}
+
+pub async fn from_url(client: &reqwest::Client, task: &DownloadTask, retry_policy: &RetryPolicy, progress: &DownloadProgress) -> Result<(), LauncherError> {
+    let mut attempt: u32 = 1;
+    loop {
+        match download(client, task, progress).await {
+            Err(e) if retry_policy.should_retry(attempt, &e) => {
+                progress.emit(DownloadEvent::TaskRetrying {
+                    url: task.url.to_owned(),
+                    attempt,
+                    error: e.to_string(),
+                });
+                tokio::time::sleep(retry_policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Ok(()) => {
+                progress.emit(DownloadEvent::TaskFinished {
+                    url: task.url.to_owned(),
+                    path: task.path.to_owned(),
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                progress.emit(DownloadEvent::TaskFailed {
+                    url: task.url.to_owned(),
+                    path: task.path.to_owned(),
+                    error: e.to_string(),
+                });
+                return Err(e);
+            }
+        }
+    }
+}
+
+// The size of a remote file from a HEAD request, retried like a download. None when the host does
+// not report one.
+pub async fn content_length(client: &reqwest::Client, url: &str, retry_policy: &RetryPolicy) -> Result<Option<u64>, LauncherError> {
+    let mut attempt: u32 = 1;
+    loop {
+        match head(client, url).await {
+            Err(e) if retry_policy.should_retry(attempt, &e) => {
+                tokio::time::sleep(retry_policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn head(client: &reqwest::Client, url: &str) -> Result<Option<u64>, LauncherError> {
+    let response = client.head(url).send().await?.error_for_status()?;
+    Ok(response.headers().get(CONTENT_LENGTH).and_then(|length| length.to_str().ok()).and_then(|length| length.parse().ok()))
+}
+
+async fn download(client: &reqwest::Client, task: &DownloadTask, progress: &DownloadProgress) -> Result<(), LauncherError> {
+    let url: &str = &task.url;
+    let path: &PathBuf = &task.path;
+    let part_path: PathBuf = part_path(path);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+
+    // Only a known SHA-1 proves the kept head and the fetched tail belong to the same file; without one
+    // a leftover part could be spliced with a newer upstream version, so it is downloaded again.
+    let resume_from: u64 = match part_path.metadata() {
+        Ok(metadata) if task.sha1.is_some() => metadata.len(),
+        _ => 0,
+    };
+    let mut request = client.get(url);
+    if resume_from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    let mut response = request.send().await?;
+
+    let mut hasher = Sha1::new();
+    let mut received: u64 = 0;
+    let resumed = resume_from > 0 && response.status() == StatusCode::PARTIAL_CONTENT && content_range_start(&response) == Some(resume_from);
+    let mut file = if resumed {
+        let mut file = OpenOptions::new().read(true).append(true).open(&part_path)?;
+        hash_reader(&mut file, &mut hasher)?;
+        received = resume_from;
+        file
+    } else {
+        // A range the server could not serve, or served from another offset, is fetched again in full.
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE || response.status() == StatusCode::PARTIAL_CONTENT {
+            response = client.get(url).send().await?;
+        }
+        response = response.error_for_status()?;
+        File::create(&part_path)?
     };
-    let content = response.bytes().await?;
-    match file.write_all(&*content) {
-        Err(e) => println!("{}", e),
-        Ok(()) => println!("Downloaded {url} to {path}", url = url, path = path_str)
+
+    let total_bytes: Option<u64> = match response.content_length() {
+        Some(length) => Some(received + length),
+        None => task.size,
     };
+    progress.emit(DownloadEvent::TaskStarted {
+        url: url.to_string(),
+        path: path.to_owned(),
+        total_bytes,
+    });
+
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+        received += chunk.len() as u64;
+        progress.emit(DownloadEvent::BytesReceived {
+            url: url.to_string(),
+            received,
+            total_bytes,
+        });
+    }
+    file.sync_all()?;
+    drop(file);
+
+    if let Some(expected) = &task.sha1 {
+        let actual = format!("{:x}", hasher.finalize());
+        if !actual.eq_ignore_ascii_case(expected) {
+            std::fs::remove_file(&part_path)?;
+            return Err(LauncherError::HashMismatch {
+                url: url.to_string(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+    }
+    std::fs::rename(&part_path, path)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// The first byte of a 206 response, from `Content-Range: bytes <start>-<end>/<total>`.
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let content_range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = content_range.strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap().to_owned();
+    file_name.push(".part");
+    path.with_file_name(file_name)
+}
+
+fn hash_reader<R: Read>(reader: &mut R, hasher: &mut Sha1) -> std::io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+pub fn sha1_file(path: &PathBuf) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    hash_reader(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn verify_file(path: &PathBuf, size: u64, sha1: &str, mode: VerifyMode) -> bool {
+    match path.metadata() {
+        Ok(metadata) => {
+            if metadata.len() != size {
+                return false;
+            }
+        }
+        Err(_) => return false,
+    }
+    match mode {
+        VerifyMode::Size => true,
+        VerifyMode::Sha1 => match sha1_file(path) {
+            Ok(actual) => actual.eq_ignore_ascii_case(sha1),
+            Err(_) => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_dir, Ranges, TestServer};
+
+    const BODY: &[u8] = b"the quick brown fox jumps over the lazy dog";
+
+    fn body_sha1() -> String {
+        format!("{:x}", Sha1::digest(BODY))
+    }
+
+    // A target path with the first `head` bytes of the body already in its part file.
+    fn partial(name: &str, head: &[u8]) -> PathBuf {
+        let path = temp_dir(name).join("file.bin");
+        std::fs::write(part_path(&path), head).unwrap();
+        path
+    }
+
+    async fn fetch(url: &str, path: &PathBuf, sha1: Option<&str>) -> Result<(), LauncherError> {
+        let mut task = DownloadTask::new(url, path.to_owned());
+        if let Some(sha1) = sha1 {
+            task = task.with_sha1(sha1);
+        }
+        from_url(&reqwest::Client::new(), &task, &RetryPolicy::none(), &DownloadProgress::new()).await
+    }
+
+    fn ranges(server: &TestServer) -> Vec<Option<String>> {
+        server.requests().into_iter().map(|request| request.range).collect()
+    }
+
+    #[tokio::test]
+    async fn resumes_a_verified_part_with_a_range_request() {
+        let server = TestServer::start(Vec::from([("/file", BODY.to_vec())]));
+        let path = partial("resume", &BODY[..10]);
+        fetch(&server.url("/file"), &path, Some(&body_sha1())).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert!(!part_path(&path).exists());
+        assert_eq!(ranges(&server), Vec::from([Some(String::from("bytes=10-"))]));
+        assert_eq!(server.requests()[0].path, "/file");
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn part_without_a_sha1_is_downloaded_again() {
+        let server = TestServer::start(Vec::from([("/file", BODY.to_vec())]));
+        let path = partial("unverified", b"stale bytes");
+        fetch(&server.url("/file"), &path, None).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert_eq!(ranges(&server), Vec::from([None]));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_range_restarts_the_download() {
+        let server = TestServer::start(Vec::from([("/file", BODY.to_vec())]));
+        let path = partial("unsatisfiable", &[b'x'; 64]);
+        fetch(&server.url("/file"), &path, Some(&body_sha1())).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert_eq!(ranges(&server), Vec::from([Some(String::from("bytes=64-")), None]));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn full_response_to_a_range_request_replaces_the_part() {
+        let server = TestServer::start_with_ranges(Vec::from([("/file", BODY.to_vec())]), Ranges::Ignore);
+        let path = partial("ignored", &BODY[..10]);
+        fetch(&server.url("/file"), &path, Some(&body_sha1())).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert_eq!(ranges(&server), Vec::from([Some(String::from("bytes=10-"))]));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn range_served_from_another_offset_restarts_the_download() {
+        let server = TestServer::start_with_ranges(Vec::from([("/file", BODY.to_vec())]), Ranges::Shift(4));
+        let path = partial("shifted", &BODY[..10]);
+        fetch(&server.url("/file"), &path, Some(&body_sha1())).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert_eq!(ranges(&server), Vec::from([Some(String::from("bytes=10-")), None]));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sha1_mismatch_discards_the_part() {
+        let server = TestServer::start(Vec::from([("/file", BODY.to_vec())]));
+        let path = temp_dir("mismatch").join("file.bin");
+        let result = fetch(&server.url("/file"), &path, Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")).await;
+        assert!(matches!(result, Err(LauncherError::HashMismatch { .. })));
+        assert!(!path.exists());
+        assert!(!part_path(&path).exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
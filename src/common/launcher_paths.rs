@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+use crate::common::error::LauncherError;
+
+pub const DATA_DIR_ENV: &str = "DUCK_LAUNCHER_HOME";
+pub const PORTABLE_MARKER: &str = "portable.txt";
+const APPLICATION_DIR: &str = "duck-launcher";
+
+#[derive(Debug, Clone)]
+pub struct LauncherPaths {
+    root: PathBuf,
+}
+
+impl LauncherPaths {
+    pub fn new(root: PathBuf) -> LauncherPaths {
+        LauncherPaths { root }
+    }
+
+    // Resolution order: explicit data dir (CLI flag), DUCK_LAUNCHER_HOME, a portable.txt marker next
+    // to the executable, then the platform data directory (XDG_DATA_HOME on Linux).
+    pub fn resolve(data_dir: Option<PathBuf>) -> Result<LauncherPaths, LauncherError> {
+        if let Some(data_dir) = data_dir {
+            return Ok(LauncherPaths::new(data_dir));
+        }
+        if let Some(data_dir) = std::env::var_os(DATA_DIR_ENV) {
+            if !data_dir.is_empty() {
+                return Ok(LauncherPaths::new(PathBuf::from(data_dir)));
+            }
+        }
+        if let Some(portable_dir) = LauncherPaths::portable_dir() {
+            return Ok(LauncherPaths::new(portable_dir));
+        }
+        match dirs::data_dir() {
+            Some(data_dir) => Ok(LauncherPaths::new(data_dir.join(APPLICATION_DIR))),
+            None => Err(LauncherError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "Unable to determine a data directory, pass one explicitly"))),
+        }
+    }
+
+    fn portable_dir() -> Option<PathBuf> {
+        let executable = std::env::current_exe().ok()?;
+        let executable_dir = executable.parent()?;
+        if executable_dir.join(PORTABLE_MARKER).is_file() {
+            Some(executable_dir.to_path_buf())
+        } else {
+            None
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn join(&self, vec: Vec<&str>) -> PathBuf {
+        let mut dir = self.root.to_path_buf();
+        for s in vec {
+            dir.push(s);
+        }
+        dir
+    }
+
+    pub fn library(&self, path: &str) -> PathBuf {
+        let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
+        for path in path.split('/') {
+            path_vector.push(path);
+        }
+        self.join(path_vector)
+    }
+}
@@ -1,11 +1,8 @@
-use std::path::PathBuf;
-
+pub mod download_progress;
+pub mod download_scheduler;
+pub mod error;
 pub mod file_downloader;
-
-pub fn join_directories(vec: Vec<&str>) -> std::io::Result<PathBuf> {
-    let mut dir = std::env::current_dir()?;
-    for s in vec {
-        dir.push(s);
-    }
-    Ok(dir)
-}
\ No newline at end of file
+pub mod launcher_paths;
+pub mod retry_policy;
+#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+use crate::common::error::LauncherError;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: bool,
+    retryable_statuses: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: true,
+            retryable_statuses: Vec::from([
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ]),
+        }
+    }
+
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new().with_max_attempts(1)
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> RetryPolicy {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> RetryPolicy {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> RetryPolicy {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_retryable_statuses(mut self, retryable_statuses: Vec<StatusCode>) -> RetryPolicy {
+        self.retryable_statuses = retryable_statuses;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn should_retry(&self, attempt: u32, error: &LauncherError) -> bool {
+        attempt < self.max_attempts && self.is_retryable(error)
+    }
+
+    pub fn is_retryable(&self, error: &LauncherError) -> bool {
+        match error {
+            LauncherError::Network(e) => match e.status() {
+                Some(status) => self.retryable_statuses.contains(&status),
+                None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            },
+            LauncherError::HashMismatch { .. } => true,
+            _ => false,
+        }
+    }
+
+    // Exponential backoff for the given attempt (starting at 1), with "equal jitter":
+    // half of the delay is fixed and the other half is random.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max_backoff.as_secs_f64());
+        if self.jitter && delay > 0.0 {
+            let half = delay / 2.0;
+            Duration::from_secs_f64(half + rand::thread_rng().gen_range(0.0..=half))
+        } else {
+            Duration::from_secs_f64(delay)
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::TestServer;
+
+    async fn status_error(path: &str) -> LauncherError {
+        let server = TestServer::start(Vec::new());
+        let response = reqwest::get(&server.url(path)).await.unwrap();
+        LauncherError::Network(response.error_for_status().unwrap_err())
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_equal_jitter_bounds() {
+        let policy = RetryPolicy::new();
+        for (attempt, delay) in Vec::from([(1, 0.5), (2, 1.0), (3, 2.0), (4, 4.0)]).into_iter() {
+            for _ in 0..20 {
+                let backoff = policy.backoff(attempt).as_secs_f64();
+                assert!(backoff >= delay / 2.0 && backoff <= delay, "attempt {} waited {}s", attempt, backoff);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy::new().with_jitter(false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(7), Duration::from_secs(30));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(30));
+        let jittered = RetryPolicy::new().backoff(50).as_secs_f64();
+        assert!((15.0..=30.0).contains(&jittered));
+    }
+
+    #[tokio::test]
+    async fn only_listed_statuses_are_retried() {
+        let not_found = status_error("/missing").await;
+        assert!(!RetryPolicy::new().is_retryable(&not_found));
+        assert!(RetryPolicy::new().with_retryable_statuses(Vec::from([StatusCode::NOT_FOUND])).is_retryable(&not_found));
+    }
+
+    #[tokio::test]
+    async fn transport_errors_and_corrupt_downloads_are_retried() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let refused = LauncherError::Network(reqwest::get(&url).await.unwrap_err());
+        let policy = RetryPolicy::new();
+        assert!(policy.is_retryable(&refused));
+        assert!(policy.is_retryable(&LauncherError::HashMismatch { url, expected: String::new(), actual: String::new() }));
+        assert!(!policy.is_retryable(&LauncherError::Io(std::io::Error::other("disk full"))));
+    }
+
+    #[test]
+    fn attempts_stop_at_the_maximum() {
+        let policy = RetryPolicy::new().with_max_attempts(3);
+        let corrupt = LauncherError::HashMismatch { url: String::new(), expected: String::new(), actual: String::new() };
+        assert!(policy.should_retry(2, &corrupt));
+        assert!(!policy.should_retry(3, &corrupt));
+        assert!(!RetryPolicy::none().should_retry(1, &corrupt));
+    }
+}
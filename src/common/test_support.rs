@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::launcher_paths::LauncherPaths;
+
+// How the stand-in answers a `Range` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranges {
+    Honour,
+    Ignore,
+    // Answers 206 but starts that many bytes before the requested offset.
+    Shift(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    pub method: String,
+    pub host: String,
+    pub path: String,
+    pub range: Option<String>,
+}
+
+// Requests being answered, overall and per Host header, and the most seen at once.
+#[derive(Debug, Default)]
+struct Connections {
+    active: usize,
+    max_active: usize,
+    active_per_host: HashMap<String, usize>,
+    max_active_per_host: HashMap<String, usize>,
+}
+
+struct ServerState {
+    routes: HashMap<String, Vec<u8>>,
+    ranges: Ranges,
+    delay: Duration,
+    requests: Mutex<Vec<TestRequest>>,
+    connections: Mutex<Connections>,
+}
+
+// A local stand-in for the download endpoints: serves fixed bodies by request path, 404 otherwise.
+// Every connection is answered on its own thread, so concurrent downloads overlap like on a real host.
+pub struct TestServer {
+    base_url: String,
+    state: Arc<ServerState>,
+}
+
+impl TestServer {
+    pub fn start(routes: Vec<(&str, Vec<u8>)>) -> TestServer {
+        TestServer::start_with(|_| routes.into_iter().map(|(path, body)| (path.to_string(), body)).collect())
+    }
+
+    // For documents that link back to the server, the routes are built from its base URL.
+    pub fn start_with<F: FnOnce(&str) -> Vec<(String, Vec<u8>)>>(routes: F) -> TestServer {
+        TestServer::spawn(routes, Ranges::Honour, Duration::from_millis(0))
+    }
+
+    pub fn start_with_ranges(routes: Vec<(&str, Vec<u8>)>, ranges: Ranges) -> TestServer {
+        TestServer::spawn(|_| routes.into_iter().map(|(path, body)| (path.to_string(), body)).collect(), ranges, Duration::from_millis(0))
+    }
+
+    // Holds every response for `delay`, long enough for concurrent requests to overlap.
+    pub fn start_with_delay(routes: Vec<(&str, Vec<u8>)>, delay: Duration) -> TestServer {
+        TestServer::spawn(|_| routes.into_iter().map(|(path, body)| (path.to_string(), body)).collect(), Ranges::Honour, delay)
+    }
+
+    fn spawn<F: FnOnce(&str) -> Vec<(String, Vec<u8>)>>(routes: F, ranges: Ranges, delay: Duration) -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(ServerState {
+            routes: routes(&base_url).into_iter().collect(),
+            ranges,
+            delay,
+            requests: Mutex::new(Vec::new()),
+            connections: Mutex::new(Connections::default()),
+        });
+        let server_state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&server_state);
+                std::thread::spawn(move || serve(stream, &state));
+            }
+        });
+        TestServer { base_url, state }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    // The same server under a different host name, which a client treats as another host.
+    pub fn localhost_url(&self, path: &str) -> String {
+        self.url(path).replacen("127.0.0.1", "localhost", 1)
+    }
+
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.state.requests.lock().unwrap().to_owned()
+    }
+
+    pub fn max_active(&self) -> usize {
+        self.state.connections.lock().unwrap().max_active
+    }
+
+    pub fn max_active_for(&self, host: &str) -> usize {
+        self.state.connections.lock().unwrap().max_active_per_host.get(host).copied().unwrap_or(0)
+    }
+}
+
+fn serve(mut stream: TcpStream, state: &ServerState) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut host = String::new();
+    let mut range: Option<String> = None;
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(read) if read > 2 => {
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("range") {
+                        range = Some(value.trim().to_string());
+                    } else if name.eq_ignore_ascii_case("host") {
+                        host = value.trim().split(':').next().unwrap_or_default().to_string();
+                    }
+                }
+            }
+            _ => break,
+        }
+    }
+    let method = request_line.split_whitespace().next().unwrap_or("GET").to_string();
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+    state.requests.lock().unwrap().push(TestRequest {
+        method: method.to_owned(),
+        host: host.to_owned(),
+        path: path.to_owned(),
+        range: range.to_owned(),
+    });
+    {
+        let mut connections = state.connections.lock().unwrap();
+        connections.active += 1;
+        connections.max_active = connections.max_active.max(connections.active);
+        let active = *connections.active_per_host.entry(host.to_owned()).and_modify(|active| *active += 1).or_insert(1);
+        let max_active = connections.max_active_per_host.entry(host.to_owned()).or_insert(0);
+        *max_active = (*max_active).max(active);
+    }
+    std::thread::sleep(state.delay);
+    let mut response = respond(state, &path, range.as_deref());
+    // A HEAD response keeps the headers of the GET one, Content-Length included, without the body.
+    if method == "HEAD" {
+        if let Some(end) = response.windows(4).position(|window| window == b"\r\n\r\n") {
+            response.truncate(end + 4);
+        }
+    }
+    {
+        let mut connections = state.connections.lock().unwrap();
+        connections.active -= 1;
+        if let Some(active) = connections.active_per_host.get_mut(&host) {
+            *active -= 1;
+        }
+    }
+    let _ = stream.write_all(&response);
+}
+
+fn respond(state: &ServerState, path: &str, range: Option<&str>) -> Vec<u8> {
+    let ranges = state.ranges;
+    let routes = &state.routes;
+    let start: Option<u64> = range.and_then(|range| range.strip_prefix("bytes=")).and_then(|range| range.trim_end_matches('-').parse().ok());
+    match (routes.get(path), start) {
+        (None, _) => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+        (Some(body), Some(start)) if ranges != Ranges::Ignore => {
+            if start >= body.len() as u64 {
+                format!("HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", body.len()).into_bytes()
+            } else {
+                let start = match ranges {
+                    Ranges::Shift(shift) => start.saturating_sub(shift),
+                    _ => start,
+                } as usize;
+                let head = format!("HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", start, body.len() - 1, body.len(), body.len() - start);
+                [head.into_bytes(), body[start..].to_vec()].concat()
+            }
+        }
+        (Some(body), _) => [format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).into_bytes(), body.to_owned()].concat(),
+    }
+}
+
+// A fresh directory under the system temp directory, unique per test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let root: PathBuf = std::env::temp_dir().join(format!("duck-launcher-{}-{}-{}", name, std::process::id(), COUNTER.fetch_add(1, Ordering::SeqCst)));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
+// A data directory of its own for a test.
+pub fn temp_paths(name: &str) -> LauncherPaths {
+    LauncherPaths::new(temp_dir(name))
+}
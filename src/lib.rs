@@ -0,0 +1,49 @@
+//! Duck Launcher as a library.
+//!
+//! The crate exposes everything the `duck-launcher` binary is built on:
+//!
+//! * version resolution: [`VersionManifest::get`] and [`Version::get_version`]
+//! * installing a version: [`Version::install`], driven by a [`DownloadScheduler`]
+//! * authentication against Yggdrasil: [`yggdrasil::authenticate`] and friends, stored as [`Accounts`]
+//! * instances with their own game directory and settings: [`Instance`], launched with [`Instance::launch`]
+//! * launching a bare version: [`minecraft::launch_client`]
+//! * following a running game: [`GameProcess`], with its output, state and a way to kill it
+//! * running a server instance: [`Instance::launch_server`], commanded through its [`GameConsole`]
+//!
+//! Every function that touches the disk takes a [`LauncherPaths`] describing the data directory.
+//!
+//! ```no_run
+//! use duck_launcher::{yggdrasil, Account, Accounts, DownloadScheduler, Instance, LauncherError, LauncherPaths};
+//!
+//! # async fn example() -> Result<(), LauncherError> {
+//! let paths = LauncherPaths::resolve(None)?;
+//! let scheduler = DownloadScheduler::new();
+//! let response = yggdrasil::authenticate("email", "password", "").await?;
+//! let mut accounts = Accounts::load(&paths)?;
+//! accounts.add(Account::from_authenticate_response("email", response)?);
+//! accounts.save(&paths)?;
+//! let instance = Instance::new("survival", "1.16.5", "email");
+//! instance.save(&paths)?;
+//! let game = instance.launch(&paths, &scheduler, None).await?;
+//! let mut stdout = game.stdout();
+//! while let Some(line) = stdout.recv().await {
+//!     println!("{}", line);
+//! }
+//! println!("Game {}", game.wait().await);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod common;
+pub mod minecraft;
+
+pub use crate::common::download_scheduler::{DownloadReport, DownloadScheduler, DownloadTask};
+pub use crate::common::error::LauncherError;
+pub use crate::common::launcher_paths::LauncherPaths;
+pub use crate::minecraft::account::{Account, Accounts};
+pub use crate::minecraft::dependency::LibrariesMetadata;
+pub use crate::minecraft::instance::Instance;
+pub use crate::minecraft::process::{GameConsole, GameProcess, GameState};
+pub use crate::minecraft::version::Version;
+pub use crate::minecraft::version_manifest::VersionManifest;
+pub use crate::minecraft::yggdrasil;
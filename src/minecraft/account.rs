@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::yggdrasil::{AuthenticateResponse, AuthenticateResponseProfile};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account{
+    pub access_token: String,
+    pub client_token: String,
+    pub active_profile: String,
+    pub profiles: Vec<Profile>,
+    pub user: User,
+    pub username: String
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Profile{
+    pub id: String,
+    pub legacy: bool,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct User{
+    pub id: String
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Accounts{
+    pub version: u8,
+    pub accounts: Vec<Account>,
+}
+
+impl Account{
+    pub fn from_authenticate_response(username: &str, response: AuthenticateResponse) -> Result<Account, LauncherError> {
+        let selected_profile = match response.selected_profile {
+            Some(profile) => Profile::from(profile),
+            None => return Err(LauncherError::Auth(String::from("Account has no selected profile"))),
+        };
+        let mut profiles: Vec<Profile> = Vec::new();
+        for profile in response.available_profiles.unwrap_or_default() {
+            profiles.push(Profile::from(profile));
+        }
+        if !profiles.iter().any(|profile| profile.id.eq(&selected_profile.id)) {
+            profiles.push(selected_profile.to_owned());
+        }
+        Ok(Account{
+            access_token: response.access_token.unwrap_or_default(),
+            client_token: response.client_token.unwrap_or_default(),
+            active_profile: selected_profile.id,
+            profiles,
+            user: User{
+                id: response.user.and_then(|user| user.id).unwrap_or_default()
+            },
+            username: username.to_string()
+        })
+    }
+
+    pub fn get_active_profile(&self) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.id.eq(&self.active_profile))
+    }
+}
+
+impl From<AuthenticateResponseProfile> for Profile{
+    fn from(profile: AuthenticateResponseProfile) -> Self {
+        Profile{
+            id: profile.id.unwrap_or_default(),
+            legacy: profile.legacy.or(profile.legacy_profile).unwrap_or(false),
+            name: profile.name.unwrap_or_default(),
+        }
+    }
+}
+
+impl Accounts{
+    pub fn load(paths: &LauncherPaths) -> Result<Accounts, LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["accounts.json"]));
+        if !path.exists() {
+            return Ok(Accounts{
+                version: 1,
+                accounts: vec![],
+            });
+        }
+        let mut file = File::open(path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        let accounts: Accounts = serde_json::from_str(&data)?;
+        Ok(accounts)
+    }
+
+    pub fn save(&self, paths: &LauncherPaths) -> Result<(), LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["accounts.json"]));
+        std::fs::create_dir_all(paths.root())?;
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, username: &str) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.username.eq(username))
+    }
+
+    // Picks the account with the given username, or the only stored account when none is given.
+    pub fn resolve(&self, username: Option<&str>) -> Result<&Account, LauncherError> {
+        match username {
+            Some(username) => match self.get(username) {
+                Some(account) => Ok(account),
+                None => Err(LauncherError::Auth(format!("Account {} not found", username))),
+            },
+            None if self.accounts.len() == 1 => Ok(&self.accounts[0]),
+            None if self.accounts.is_empty() => Err(LauncherError::Auth(String::from("No account stored"))),
+            None => Err(LauncherError::Auth(String::from("Several accounts stored, select one"))),
+        }
+    }
+
+    pub fn add(&mut self, account: Account) {
+        self.remove(&account.username);
+        self.accounts.push(account);
+    }
+
+    pub fn remove(&mut self, username: &str) -> Option<Account> {
+        let index = self.accounts.iter().position(|account| account.username.eq(username))?;
+        Some(self.accounts.remove(index))
+    }
+}
@@ -3,116 +3,129 @@ use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 
-use futures::StreamExt;
 use serde::Deserialize;
 
-use crate::common;
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
 use crate::minecraft::version::Version;
 
 const MINECRAFT_RESOURCES: &str = "http://resources.download.minecraft.net";
 
 #[derive(Debug, Deserialize)]
 pub struct AssetIndex {
-    map_to_resources: Option<bool>,
-    objects: HashMap<String, AssetIndexObject>,
-    r#virtual: Option<bool>,
+    pub map_to_resources: Option<bool>,
+    pub objects: HashMap<String, AssetIndexObject>,
+    pub r#virtual: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
-struct AssetIndexObject {
-    hash: String,
-    size: u64,
+pub struct AssetIndexObject {
+    pub hash: String,
+    pub size: u64,
 }
 
 impl Version{
-    pub async fn verify_assets(&self) -> Result<(), reqwest::Error> {
-        let path: PathBuf = common::join_directories(Vec::from(["assets", "indexes", &*format!("{}.json", &self.assets)])).unwrap();
-        return if path.exists() {
-            if path.metadata().unwrap().len() != self.asset_index.size {
-                self.fetch_assets_index(&path).await
-            } else {
-                self.fetch_assets(&path).await
-            }
-        } else {
-            self.fetch_assets_index(&path).await
-        };
+    pub async fn verify_assets(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["assets", "indexes", &*format!("{}.json", &self.assets)]));
+        let task = DownloadTask::new(&self.asset_index.url, path.to_owned()).with_sha1(&self.asset_index.sha1).with_size(self.asset_index.size);
+        if !scheduler.is_valid(&task) {
+            scheduler.download(task).await?;
+        }
+        scheduler.submit_invalid("assets", self.get_assets_tasks(paths, &path)?);
+        Ok(())
     }
 
-    async fn fetch_assets_index(&self, path: &PathBuf) -> Result<(), reqwest::Error> {
-        match common::file_downloader::from_url(&self.asset_index.url, &path).await {
-            Ok(()) => return self.fetch_assets(&path).await,
-            Err(e) => panic!("{}", e)
+    // Legacy versions read their assets from ${game_assets}, which is the virtual directory for
+    // indexes that are marked virtual.
+    pub fn get_game_assets_path(&self, paths: &LauncherPaths) -> PathBuf {
+        let path: PathBuf = paths.join(Vec::from(["assets", "indexes", &*format!("{}.json", &self.assets)]));
+        let is_virtual = std::fs::read_to_string(path).ok()
+            .and_then(|data| serde_json::from_str::<AssetIndex>(&data).ok())
+            .and_then(|asset_index| asset_index.r#virtual)
+            .unwrap_or(false);
+        if is_virtual {
+            paths.join(Vec::from(["assets", "virtual", &self.assets]))
+        } else {
+            paths.join(Vec::from(["assets"]))
         }
     }
 
-    async fn fetch_assets(&self, path: &PathBuf) -> Result<(), reqwest::Error> {
-        let mut file = File::open(path).expect("Something");
+    fn get_assets_tasks(&self, paths: &LauncherPaths, path: &PathBuf) -> Result<Vec<DownloadTask>, LauncherError> {
+        let mut file = File::open(path)?;
         let mut data = String::new();
-        file.read_to_string(&mut data).expect("Unable to read file");
+        file.read_to_string(&mut data)?;
 
-        let asset_index: AssetIndex = serde_json::from_str(&data).expect("");
-        match asset_index.r#virtual {//Todo: map to resources
-            Some(_val) => {
-                let mut assets_objects: HashMap<String, AssetIndexObject> = HashMap::new();
-                for object in asset_index.objects {
-                    let mut path_vector: Vec<&str> = Vec::from(["assets", "virtual", &self.assets]);
-                    for path in object.0.split('/') {
-                        path_vector.push(path);
-                    }
-                    let path: PathBuf = common::join_directories(path_vector).unwrap();
-                    if !path.exists() || path.metadata().unwrap().len() != object.1.size {
-                        assets_objects.insert(object.0, object.1);
-                    }
+        let asset_index: AssetIndex = serde_json::from_str(&data)?;
+        let mut tasks: Vec<DownloadTask> = Vec::new();
+        // Same reading as get_game_assets_path, an index with "virtual": false keeps the hashed layout.
+        let is_virtual = asset_index.r#virtual.unwrap_or(false);
+        for object in asset_index.objects {
+            let path: PathBuf = if is_virtual {//Todo: map to resources
+                let mut path_vector: Vec<&str> = Vec::from(["assets", "virtual", &self.assets]);
+                for path in object.0.split('/') {
+                    path_vector.push(path);
                 }
+                paths.join(path_vector)
+            } else {
+                paths.join(Vec::from(["assets", "objects", &object.1.hash[0..2], &object.1.hash]))
+            };
+            let url = format!("{api}/{two_hash}/{complete_hash}", api = MINECRAFT_RESOURCES, two_hash = &object.1.hash[0..2], complete_hash = &object.1.hash);
+            tasks.push(DownloadTask::new(&url, path).with_sha1(&object.1.hash).with_size(object.1.size));
+        }
+        Ok(tasks)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::temp_paths;
 
-                let assets_objects_size = assets_objects.len().to_owned();
-                if assets_objects_size > 0 {
-                    let fetches = futures::stream::iter(
-                        assets_objects.into_iter().map(|object| {
-                            async move {
-                                let mut path_vector: Vec<&str> = Vec::from(["assets", "virtual", &self.assets]);
-                                for path in object.0.split('/') {
-                                    path_vector.push(path);
-                                }
-                                let path: PathBuf = common::join_directories(path_vector).unwrap();
-                                let url = format!("{api}/{two_hash}/{complete_hash}", api = MINECRAFT_RESOURCES, two_hash = &object.1.hash[0..2], complete_hash = &object.1.hash);
-                                match common::file_downloader::from_url(&url, &path).await {
-                                    Ok(()) => {}
-                                    Err(e) => panic!("{}", e)
-                                }
-                            }
-                        })
-                    ).buffer_unordered(assets_objects_size).collect::<Vec<()>>();//sips memory todo all buffer
-                    fetches.await;
-                }
-            }
-            None => {
-                let mut assets_objects: HashMap<String, AssetIndexObject> = HashMap::new();
-                for object in asset_index.objects {
-                    let path: PathBuf = common::join_directories(Vec::from(["assets", "objects", &object.1.hash[0..2], &object.1.hash])).unwrap();
-                    if !path.exists() || path.metadata().unwrap().len() != object.1.size {
-                        assets_objects.insert(object.0, object.1);
-                    }
-                }
+    const HASH: &str = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
 
-                let assets_objects_size = assets_objects.len().to_owned();
-                if assets_objects_size > 1 {
-                    let fetches = futures::stream::iter(
-                        assets_objects.into_iter().map(|object| {
-                            async move {
-                                let path: PathBuf = common::join_directories(Vec::from(["assets", "objects", &object.1.hash[0..2], &object.1.hash])).unwrap();
-                                let url = format!("{api}/{two_hash}/{complete_hash}", api = MINECRAFT_RESOURCES, two_hash = &object.1.hash[0..2], complete_hash = &object.1.hash);
-                                match common::file_downloader::from_url(&url, &path).await {
-                                    Ok(()) => {}
-                                    Err(e) => panic!("{}", e)
-                                }
-                            }
-                        })
-                    ).buffer_unordered(assets_objects_size).collect::<Vec<()>>();
-                    fetches.await;
-                }
-            }
+    fn version(assets: &str) -> Version {
+        serde_json::from_value(serde_json::json!({
+            "assetIndex": { "id": assets, "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": assets,
+            "downloads": {},
+            "id": "1.7.10",
+            "libraries": [],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 13,
+            "releaseTime": "2014-05-14T17:29:23+00:00",
+            "time": "2014-05-14T17:29:23+00:00",
+            "type": "release"
+        })).unwrap()
+    }
+
+    // Writes an asset index with a single object, with or without a "virtual" field.
+    fn write_index(paths: &LauncherPaths, assets: &str, r#virtual: Option<bool>) -> PathBuf {
+        let mut index = serde_json::json!({ "objects": { "sounds/random/click.ogg": { "hash": HASH, "size": 4 } } });
+        if let Some(r#virtual) = r#virtual {
+            index["virtual"] = serde_json::Value::from(r#virtual);
         }
-        Ok(())
+        let path = paths.join(Vec::from(["assets", "indexes", &*format!("{}.json", assets)]));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, index.to_string()).unwrap();
+        path
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn only_virtual_indexes_use_the_virtual_layout() {
+        let paths = temp_paths("asset-layout");
+        let objects = paths.join(Vec::from(["assets", "objects", &HASH[0..2], HASH]));
+        for (assets, r#virtual, expected, game_assets) in Vec::from([
+            ("legacy", Some(true), paths.join(Vec::from(["assets", "virtual", "legacy", "sounds", "random", "click.ogg"])), paths.join(Vec::from(["assets", "virtual", "legacy"]))),
+            ("flagged", Some(false), objects.to_owned(), paths.join(Vec::from(["assets"]))),
+            ("modern", None, objects.to_owned(), paths.join(Vec::from(["assets"]))),
+        ]) {
+            let version = version(assets);
+            let index = write_index(&paths, assets, r#virtual);
+            let tasks = version.get_assets_tasks(&paths, &index).unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].path, expected, "{}", assets);
+            assert_eq!(tasks[0].url, format!("{}/{}/{}", MINECRAFT_RESOURCES, &HASH[0..2], HASH));
+            assert_eq!(version.get_game_assets_path(&paths), game_assets, "{}", assets);
+        }
+    }
+}
@@ -1,228 +1,218 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use serde::__private::Option::Some;
 
-use crate::common;
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::forge;
+use crate::minecraft::rule;
+use crate::minecraft::rule::{RuleAction, RuleContext, RuleOs};
 use crate::minecraft::version::{Version, VersionLibrary, VersionLibraryDownloadObject};
 
 pub struct Dependency {
     pub group: String,
     pub artifact: String,
     pub version: String,
+    pub classifier: Option<String>,
+    pub extension: String,
 }
 
 impl Dependency {
-    pub fn from_version_library(version_library: &VersionLibrary) -> Option<Dependency> {
-        let split = version_library.name.split(':').collect::<Vec<&str>>();
-        if split.len() != 3 {
-            None
-        } else {
-            let group = {
-                match split.get(0) {
-                    Some(val) => val.to_owned().to_string(),
-                    None => return None
-                }
-            };
-            let artifact = {
-                match split.get(1) {
-                    Some(val) => val.to_owned().to_string(),
-                    None => return None
-                }
-            };
-            let version = {
-                match split.get(2) {
-                    Some(val) => val.to_owned().to_string(),
-                    None => return None
-                }
-            };
-            let dependency = Dependency {
-                group,
-                artifact,
-                version,
-            };
-            Some(dependency)
+    // Maven coordinates: group:artifact:version[:classifier][@extension]
+    pub fn parse(name: &str) -> Option<Dependency> {
+        let (coordinates, extension) = match name.split_once('@') {
+            Some((coordinates, extension)) => (coordinates, extension),
+            None => (name, "jar"),
+        };
+        let split = coordinates.split(':').collect::<Vec<&str>>();
+        if split.len() != 3 && split.len() != 4 {
+            return None;
         }
+        if split.iter().any(|part| part.is_empty()) || extension.is_empty() {
+            return None;
+        }
+        Some(Dependency {
+            group: split[0].to_string(),
+            artifact: split[1].to_string(),
+            version: split[2].to_string(),
+            classifier: split.get(3).map(|classifier| classifier.to_string()),
+            extension: extension.to_string(),
+        })
+    }
+
+    pub fn from_version_library(version_library: &VersionLibrary) -> Option<Dependency> {
+        Dependency::parse(&version_library.name)
     }
 
-    pub fn from_version_library_download_object(version_library: &VersionLibraryDownloadObject) -> Dependency {
-        let split = version_library.path.split("/").collect::<Vec<&str>>();
-        let version = split.get(split.len() - 2).unwrap();
-        let artifact = split.get(split.len() - 3).unwrap();
-        let group = version_library.path.split(format!("/{}/{}", artifact, version).as_str()).collect::<Vec<&str>>().get(0).unwrap().to_owned().replace("/", ".");
+    pub fn from_version_library_download_object(version_library: &VersionLibraryDownloadObject) -> Result<Dependency, LauncherError> {
+        let split = version_library.path.split('/').collect::<Vec<&str>>();
+        if split.len() < 4 {
+            return Err(LauncherError::InvalidLibrary(version_library.path.to_owned()));
+        }
+        let version = split[split.len() - 2];
+        let artifact = split[split.len() - 3];
+        let group = split[..split.len() - 3].join(".");
 
-        Dependency {
+        Ok(Dependency {
             group,
             artifact: artifact.to_string(),
             version: version.to_string(),
-        }
+            classifier: None,
+            extension: String::from("jar"),
+        })
     }
 
     pub fn to_maven_url_layout_jar(&self) -> String {
-        format!("{group}/{artifact}/{version}/{artifact}-{version}.jar", group = self.group.replace('.', "/"), artifact = self.artifact, version = self.version)
+        let classifier = match &self.classifier {
+            Some(classifier) => format!("-{}", classifier),
+            None => String::new(),
+        };
+        format!("{group}/{artifact}/{version}/{artifact}-{version}{classifier}.{extension}", group = self.group.replace('.', "/"), artifact = self.artifact, version = self.version, classifier = classifier, extension = self.extension)
+    }
+}
+
+impl VersionLibrary {
+    // Vanilla libraries name their artifact explicitly, loader libraries only give a maven repository
+    // in `url` and are laid out by their coordinates.
+    pub fn get_artifact_path(&self) -> Option<String> {
+        if let Some(artifact) = self.downloads.as_ref().and_then(|downloads| downloads.artifact.as_ref()) {
+            Some(artifact.path.to_owned())
+        } else if self.url.is_some() {
+            Dependency::from_version_library(self).map(|dependency| dependency.to_maven_url_layout_jar())
+        } else {
+            None
+        }
+    }
+
+    pub fn get_artifact_task(&self, paths: &LauncherPaths) -> Option<DownloadTask> {
+        if let Some(artifact) = self.downloads.as_ref().and_then(|downloads| downloads.artifact.as_ref()) {
+            // Forge leaves the url empty for artifacts its installer ships or generates.
+            if artifact.url.is_empty() {
+                return None;
+            }
+            return Some(DownloadTask::new(&artifact.url, paths.library(&artifact.path)).with_sha1(&artifact.sha1).with_size(artifact.size));
+        }
+        let url = self.url.as_ref()?;
+        let path = Dependency::from_version_library(self)?.to_maven_url_layout_jar();
+        let mut task = DownloadTask::new(&format!("{}/{}", url.trim_end_matches('/'), path), paths.library(&path));
+        if let Some(sha1) = &self.sha1 {
+            task = task.with_sha1(sha1);
+        }
+        if let Some(size) = self.size {
+            task = task.with_size(size);
+        }
+        Some(task)
+    }
+}
+
+impl VersionLibrary {
+    // The classifier the `natives` map names for the OS, with ${arch} replaced by the pointer width.
+    pub fn get_native_classifier(&self, context: &RuleContext) -> Option<String> {
+        let classifier = self.natives.as_ref()?.get(&context.os_name)?;
+        let arch = if context.os_arch.eq("x86") || context.os_arch.eq("arm") { "32" } else { "64" };
+        Some(classifier.replace("${arch}", arch))
+    }
+
+    // ARM builds ship as <classifier>-arm64 (LWJGL 3) or -aarch_64 next to the x86 ones.
+    pub fn get_classifier(&self, classifier: &str, context: &RuleContext) -> Option<&VersionLibraryDownloadObject> {
+        let classifiers = self.downloads.as_ref()?.classifiers.as_ref()?;
+        if context.os_arch.eq("aarch64") {
+            for suffix in Vec::from(["-arm64", "-aarch_64"]) {
+                if let Some(native) = classifiers.get(&format!("{}{}", classifier, suffix)) {
+                    return Some(native);
+                }
+            }
+        }
+        classifiers.get(classifier)
     }
 }
 
 impl Version {
-    pub async fn verify_libraries(&self) -> Result<(), reqwest::Error> {
+    pub fn verify_libraries(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        let mut tasks: Vec<DownloadTask> = Vec::new();
         if let Some(dependencies) = self.get_required_libraries() {
-            let mut future_libraries: Vec<&VersionLibrary> = Vec::new();
             for dependency in dependencies {
-                let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                if let Some(artifact) = dependency.1.1.downloads.as_ref().unwrap().artifact.as_ref() {
-                    for path in artifact.path.split('/') {
-                        path_vector.push(path);
-                    }
-                }
-                let path: PathBuf = common::join_directories(path_vector).unwrap();
-                if let Some(artifact) = dependency.1.1.downloads.as_ref().unwrap().artifact.as_ref() {
-                    if !path.exists() || path.metadata().unwrap().len() != artifact.size {
-                        future_libraries.push(dependency.1.1);
-                    }
+                if let Some(task) = dependency.1.1.get_artifact_task(paths) {
+                    tasks.push(task);
                 }
             }
-
-            let future_libraries_size = future_libraries.len().to_owned();
-            if future_libraries_size > 0 {
-                let fetches = futures::stream::iter(
-                    future_libraries.into_iter().map(|library| {
-                        async move {
-                            let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                            for path in library.downloads.as_ref().unwrap().artifact.as_ref().unwrap().path.split('/') {
-                                path_vector.push(path);
-                            }
-                            let path: PathBuf = common::join_directories(path_vector).unwrap();
-                            match common::file_downloader::from_url(&library.downloads.as_ref().unwrap().artifact.as_ref().unwrap().url, &path).await {
-                                Ok(()) => {}
-                                Err(e) => panic!("{}", e)
-                            }
-                        }
-                    })
-                ).buffer_unordered(future_libraries_size).collect::<Vec<()>>();
-                fetches.await;
-            }
         }
+        scheduler.submit_invalid("libraries", tasks);
         Ok(())
     }
 
     pub fn get_required_libraries(&self) -> Option<HashMap<&str, (Dependency, &VersionLibrary)>> {
         let mut dependencies: HashMap<&str, (Dependency, &VersionLibrary)> = HashMap::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let context = RuleContext::current();
         for library in &self.libraries {
-            let dependency_library = Dependency::from_version_library(&library).unwrap();
-            dependencies.insert(&library.name, (dependency_library, library));
-        }
-
-        let mut remove_dependencies: Vec<&str> = Vec::new();
-        for dependency_upper in &dependencies {
-            for dependency_lower in &dependencies {
-                if dependency_upper.1.0.group.eq(&dependency_lower.1.0.group) && dependency_upper.1.0.artifact.eq(&dependency_lower.1.0.artifact) {
-                    let dep_up = semver::Version::parse(&dependency_upper.1.0.version);
-                    let dep_low = semver::Version::parse(&dependency_lower.1.0.version);
-                    if dep_up > dep_low && !remove_dependencies.contains(&dependency_lower.0) {
-                        remove_dependencies.push(&dependency_lower.0)
-                    }
-                }
+            if !rule::is_allowed(library.rules.as_ref(), &context) {
+                continue;
+            }
+            // A merged version lists the child's libraries first, so the first entry for an artifact is
+            // the one the child pinned. Versions are not compared, loaders rarely use semver ones.
+            if !seen.insert(library_key(&library.name)) {
+                continue;
+            }
+            if let Some(dependency_library) = Dependency::from_version_library(library) {
+                dependencies.insert(&library.name, (dependency_library, library));
             }
-        }
-        for remove_dependency in remove_dependencies {
-            dependencies.remove(remove_dependency);
         }
         Some(dependencies)
     }
 
-    pub fn get_required_natives(&self) -> Option<Vec<&VersionLibraryDownloadObject>> {
+    pub fn get_required_natives(&self) -> Option<Vec<(&VersionLibrary, &VersionLibraryDownloadObject)>> {
         if let Some(dependencies) = self.get_required_libraries() {
-            let mut natives: Vec<&VersionLibraryDownloadObject> = Vec::new();
+            let mut natives: Vec<(&VersionLibrary, &VersionLibraryDownloadObject)> = Vec::new();
+            let context = RuleContext::current();
             for dependency in dependencies {
-                let classifiers = &dependency.1.1.downloads.as_ref().unwrap().classifiers;
-                match classifiers {
-                    Some(classifiers) => {
-                        let os = std::env::consts::OS;
-                        if os.eq("windows") && classifiers.contains_key("natives-windows") {
-                            natives.push(classifiers.get("natives-windows").unwrap());
-                        } else if os.eq("linux") && classifiers.contains_key("natives-linux") {
-                            natives.push(classifiers.get("natives-linux").unwrap());
-                        } else if os.eq("macos") && classifiers.contains_key("natives-macos") {
-                            natives.push(classifiers.get("natives-macos").unwrap());
-                        }
-                    }
-                    None => {}
+                let library = dependency.1.1;
+                if let Some(native) = library.get_native_classifier(&context).and_then(|classifier| library.get_classifier(&classifier, &context)) {
+                    natives.push((library, native));
                 }
             }
+            natives.sort_by(|(_, left), (_, right)| left.path.cmp(&right.path));
             return Some(natives);
         }
         None
     }
 
-    pub async fn verify_natives(&self) -> Result<(), reqwest::Error> {
+    pub fn verify_natives(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        let mut tasks: Vec<DownloadTask> = Vec::new();
         if let Some(natives) = self.get_required_natives() {
-            let mut future_natives: Vec<&VersionLibraryDownloadObject> = Vec::new();
-            for native in natives {
-                let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                for path in native.path.split('/') {
-                    path_vector.push(path);
-                }
-                let path: PathBuf = common::join_directories(path_vector).unwrap();
-                if !path.exists() || path.metadata().unwrap().len() != native.size {
-                    future_natives.push(native);
-                }
-            }
-
-            let future_natives_size = future_natives.to_owned().len();
-            if future_natives_size > 0 {
-                let fetches = futures::stream::iter(
-                    future_natives.into_iter().map(|native| {
-                        async move {
-                            let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                            for path in native.path.split('/') {
-                                path_vector.push(path);
-                            }
-                            let path: PathBuf = common::join_directories(path_vector).unwrap();
-                            match common::file_downloader::from_url(&native.url, &path).await {
-                                Ok(()) => {}
-                                Err(e) => panic!("{}", e)
-                            }
-                        }
-                    })
-                ).buffer_unordered(future_natives_size).collect::<Vec<()>>();
-                fetches.await;
+            for (_, native) in natives {
+                let path: PathBuf = paths.library(&native.path);
+                tasks.push(DownloadTask::new(&native.url, path).with_sha1(&native.sha1).with_size(native.size));
             }
         }
+        scheduler.submit_invalid("natives", tasks);
         Ok(())
     }
 
-    pub fn get_required_natives_paths(&self) -> Vec<PathBuf> {
-        let mut paths: Vec<PathBuf> = Vec::new();
+    pub fn get_required_natives_paths(&self, paths: &LauncherPaths) -> Vec<PathBuf> {
+        let mut natives_paths: Vec<PathBuf> = Vec::new();
         if let Some(natives) = self.get_required_natives() {
-            for native in natives {
-                let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                for path in native.path.split('/') {
-                    path_vector.push(path);
-                }
-                let path: PathBuf = common::join_directories(path_vector).unwrap();
-                paths.push(path);
+            for (_, native) in natives {
+                natives_paths.push(paths.library(&native.path));
             }
         }
-        paths
+        natives_paths
     }
 
-    pub fn get_required_libraries_paths(&self) -> Vec<PathBuf> {
-        let mut paths: Vec<PathBuf> = Vec::new();
+    pub fn get_required_libraries_paths(&self, paths: &LauncherPaths) -> Vec<PathBuf> {
+        let mut libraries_paths: Vec<PathBuf> = Vec::new();
         if let Some(library_map) = self.get_required_libraries() {
             for library in library_map {
-                let mut path_vector: Vec<&str> = Vec::from(["libraries"]);
-                for path in library.1.1.downloads.as_ref().unwrap().artifact.as_ref().unwrap().path.split('/') {
-                    path_vector.push(path);
+                if let Some(path) = library.1.1.get_artifact_path() {
+                    libraries_paths.push(paths.library(&path));
                 }
-                let path: PathBuf = common::join_directories(path_vector).unwrap();
-                paths.push(path);
             }
         }
-        paths
+        libraries_paths
     }
 }
 
@@ -269,14 +259,14 @@ pub struct LibrariesMetadataDependencyNativeRule{
 }
 
 impl LibrariesMetadata {
-    pub fn new() -> LibrariesMetadata {
-        let path: PathBuf = common::join_directories(Vec::from(["libraries", "libraries_metadata.json"])).unwrap();
+    pub fn new(paths: &LauncherPaths) -> Result<LibrariesMetadata, LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["libraries", "libraries_metadata.json"]));
         if path.exists() {
-            let mut file = File::open(path).expect("");
+            let mut file = File::open(path)?;
             let mut data = String::new();
-            file.read_to_string(&mut data).expect("Unable to read file");
-            let metadata: LibrariesMetadata = serde_json::from_str(&data).expect("JSON was not well-formatted");
-            metadata
+            file.read_to_string(&mut data)?;
+            let metadata: LibrariesMetadata = serde_json::from_str(&data)?;
+            Ok(metadata)
         } else {
             let metadata = LibrariesMetadata {
                 version: 1,
@@ -284,42 +274,32 @@ impl LibrariesMetadata {
                 clients: vec![],
                 servers: vec![],
             };
-            let file = File::create(path).expect("Unable to create file");
-            serde_json::to_writer(file, &metadata).expect("Unable to write to file");
-            metadata
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            let file = File::create(path)?;
+            serde_json::to_writer(file, &metadata)?;
+            Ok(metadata)
         }
     }
 
-    pub async fn push_mc_version(mut self, version: &Version) -> LibrariesMetadata {
+    pub async fn push_mc_version(mut self, version: &Version, scheduler: &DownloadScheduler) -> Result<LibrariesMetadata, LauncherError> {
         for library in &version.libraries {
             let id = &library.name.to_owned();
-            if self.libraries_contains(&id) {
+            if self.libraries_contains(id) {
                 continue;
             }
 
             let name = &library.name.to_owned();
 
             let size: Option<u64> = if let Some(downloads) = &library.downloads {
-                if let Some(artifact) = &downloads.artifact {
-                    Some(artifact.size)
-                } else {
-                    None
-                }
+                downloads.artifact.as_ref().map(|artifact| artifact.size)
+            } else if library.size.is_some() {
+                library.size
             } else if let Some(url) = &library.url {
-                let dependency = Dependency::from_version_library(&library);
+                let dependency = Dependency::from_version_library(library);
                 if let Some(dependency) = dependency {
-                    let url = format!("{url}/{maven_layout}", url = url, maven_layout = dependency.to_maven_url_layout_jar());
-                    let res = reqwest::get(&url).await;//fixme: really dumb
-                    match res {
-                        Ok(res) => {
-                            if let Some(length) = res.content_length() {
-                                Some(length)
-                            }else{
-                                None
-                            }
-                        },
-                        Err(e) => panic!("{}", e)
-                    }
+                    let url = format!("{url}/{maven_layout}", url = url.trim_end_matches('/'), maven_layout = dependency.to_maven_url_layout_jar());
+                    // The size is informational, a host that does not answer leaves it unknown.
+                    scheduler.content_length(&url).await.ok().flatten()
                 } else {
                     None
                 }
@@ -328,35 +308,19 @@ impl LibrariesMetadata {
             };
 
             let url: Option<String> = if let Some(downloads) = &library.downloads {
-                if let Some(artifact) = &downloads.artifact {
-                    Some(artifact.url.to_owned().to_string())
-                } else {
-                    None
-                }
+                downloads.artifact.as_ref().map(|artifact| artifact.url.to_owned().to_string())
             } else if let Some(url) = &library.url {
-                let dependency = Dependency::from_version_library(&library);
-                if let Some(dependency) = dependency {
-                    Some(format!("{url}/{maven_layout}", url = url, maven_layout = dependency.to_maven_url_layout_jar()))
-                } else {
-                    None
-                }
+                let dependency = Dependency::from_version_library(library);
+                dependency.map(|dependency| format!("{url}/{maven_layout}", url = url.trim_end_matches('/'), maven_layout = dependency.to_maven_url_layout_jar()))
             } else {
                 None
             };
 
             let path: Option<String> = if let Some(downloads) = &library.downloads {
-                if let Some(artifact) = &downloads.artifact {
-                    Some(artifact.path.to_owned().to_string())
-                } else {
-                    None
-                }
-            } else if let Some(url) = &library.url {
-                let dependency = Dependency::from_version_library(&library);
-                if let Some(dependency) = dependency {
-                    Some(dependency.to_maven_url_layout_jar())
-                } else {
-                    None
-                }
+                downloads.artifact.as_ref().map(|artifact| artifact.path.to_owned().to_string())
+            } else if library.url.is_some() {
+                let dependency = Dependency::from_version_library(library);
+                dependency.map(|dependency| dependency.to_maven_url_layout_jar())
             } else {
                 None
             };
@@ -366,7 +330,7 @@ impl LibrariesMetadata {
                     if let Some(classifiers) = &downloads.classifiers {
                         let mut map: HashMap<String, LibrariesMetadataDependency> = HashMap::new();
                         for classifier in classifiers {
-                            let dep = Dependency::from_version_library_download_object(classifier.1);
+                            let dep = Dependency::from_version_library_download_object(classifier.1)?;
                             let id = format!("{}:{}:{}", dep.group, dep.artifact, dep.version);
                             let name = id.to_owned();
                             let size = classifier.1.size.to_owned();
@@ -385,23 +349,23 @@ impl LibrariesMetadata {
                         let mut rules_map: HashMap<String, LibrariesMetadataDependencyNativeRule> = HashMap::new();
                         if let Some(rules) = &library.rules {
                             for rule in rules {
-                                let mut key;
-                                let allowed = rule.action.eq("allowed");
-                                let mut version;
-                                if let Some(os) = &rule.os{
-                                    if let Some(os_version) = &os.version{
+                                let key;
+                                let allowed = rule.action == RuleAction::Allow;
+                                let version;
+                                if let Some(RuleOs{name: Some(name), version: os_version, ..}) = &rule.os{
+                                    if let Some(os_version) = os_version{
                                         version = Some(os_version.to_string());
                                     }else{
                                         version = None;
                                     }
-                                    key = &os.name;
+                                    key = name;
                                 }else{
                                     continue;
                                 }
                                 rules_map.insert(key.to_string(), LibrariesMetadataDependencyNativeRule{allowed, version});
                             }
                         }
-                        if rules_map.len() == 0 {
+                        if rules_map.is_empty() {
                             Some(LibrariesMetadataDependencyNative{platforms: map, rules: None})
                         }else{
                             Some(LibrariesMetadataDependencyNative{platforms: map, rules: Some(rules_map)})
@@ -457,7 +421,7 @@ impl LibrariesMetadata {
                 });
             }
         }
-        self
+        Ok(self)
     }
 
     pub fn libraries_contains(&self, library_id: &String) -> bool{
@@ -499,9 +463,188 @@ impl LibrariesMetadata {
         self.servers.push(server);
     }
 
-    pub fn save(self) {
-        let path: PathBuf = common::join_directories(Vec::from(["libraries", "libraries_metadata.json"])).unwrap();
-        let file = File::create(path).expect("Unable to create file");
-        serde_json::to_writer(file, &self).expect("Unable to write to file");
+    pub fn save(self, paths: &LauncherPaths) -> Result<(), LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["libraries", "libraries_metadata.json"]));
+        let file = File::create(path)?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+// Removes every file under libraries/ that none of the given versions references, except the
+// libraries metadata itself. Returns the removed files, or the files that would be removed on a dry run.
+pub fn collect_garbage(paths: &LauncherPaths, versions: &[Version], dry_run: bool) -> Result<Vec<PathBuf>, LauncherError> {
+    let mut referenced: HashSet<PathBuf> = HashSet::new();
+    referenced.insert(paths.join(Vec::from(["libraries", "libraries_metadata.json"])));
+    for version in versions {
+        referenced.extend(version.get_required_libraries_paths(paths));
+        referenced.extend(version.get_required_natives_paths(paths));
+        referenced.insert(version.client_path(paths));
+        // Server jars are only present once a server instance of the version was installed.
+        let server_path = version.server_path(paths);
+        if server_path.is_file() {
+            referenced.insert(server_path);
+        }
+        referenced.extend(forge::installed_files(paths, &version.id));
+    }
+    let mut garbage: Vec<PathBuf> = Vec::new();
+    let root = paths.join(Vec::from(["libraries"]));
+    if root.exists() {
+        collect_unreferenced(&root, &referenced, &mut garbage)?;
+    }
+    if !dry_run {
+        for path in &garbage {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(garbage)
+}
+
+fn collect_unreferenced(dir: &Path, referenced: &HashSet<PathBuf>, garbage: &mut Vec<PathBuf>) -> Result<(), LauncherError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_unreferenced(&path, referenced, garbage)?;
+        } else if !referenced.contains(&path) {
+            garbage.push(path);
+        }
+    }
+    Ok(())
+}
+
+// `group:artifact[:classifier]`, a library name without its version.
+fn library_key(name: &str) -> String {
+    let mut parts: Vec<&str> = name.split(':').collect();
+    if parts.len() > 2 {
+        parts.remove(2);
+    }
+    parts.join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::retry_policy::RetryPolicy;
+    use crate::common::test_support::{temp_paths, TestServer};
+
+    fn version(libraries: &[&str]) -> Version {
+        version_of(libraries.iter().map(|name| serde_json::json!({ "name": name })).collect())
+    }
+
+    fn version_of(libraries: Vec<serde_json::Value>) -> Version {
+        serde_json::from_value(serde_json::json!({
+            "assetIndex": { "id": "1.16", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": "1.16",
+            "downloads": {},
+            "id": "forge-36.1.0",
+            "libraries": libraries,
+            "mainClass": "cpw.mods.modlauncher.Launcher",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2021-03-01T00:00:00+00:00",
+            "time": "2021-03-01T00:00:00+00:00",
+            "type": "release"
+        })).unwrap()
+    }
+
+    fn required_versions(version: &Version) -> Vec<(String, String)> {
+        let mut required: Vec<(String, String)> = version.get_required_libraries().unwrap().values().map(|(dependency, _)| (format!("{}:{}", dependency.group, dependency.artifact), dependency.version.to_owned())).collect();
+        required.sort();
+        required
+    }
+
+    #[test]
+    fn the_first_library_of_an_artifact_wins() {
+        let version = version(&[
+            "org.ow2.asm:asm:9.1",
+            "net.minecraftforge:forge:1.16.5-36.1.0",
+            "com.google.guava:guava:21.0",
+            "org.ow2.asm:asm:7.2",
+            "net.minecraftforge:forge:1.16.5-36.0.0",
+            "com.google.guava:guava:31.0.1-jre",
+            "com.mojang:brigadier:1.0.17",
+        ]);
+        assert_eq!(required_versions(&version), Vec::from([
+            ("com.google.guava:guava".to_string(), "21.0".to_string()),
+            ("com.mojang:brigadier".to_string(), "1.0.17".to_string()),
+            ("net.minecraftforge:forge".to_string(), "1.16.5-36.1.0".to_string()),
+            ("org.ow2.asm:asm".to_string(), "9.1".to_string()),
+        ]));
+    }
+
+    #[tokio::test]
+    async fn maven_libraries_are_sized_with_a_head_request() {
+        let server = TestServer::start(Vec::from([("/maven/net/fabricmc/fabric-loader/0.11.3/fabric-loader-0.11.3.jar", b"not really a jar".to_vec())]));
+        let paths = temp_paths("libraries-metadata");
+        let version = version_of(Vec::from([
+            serde_json::json!({ "name": "net.fabricmc:fabric-loader:0.11.3", "url": server.url("/maven/") }),
+            serde_json::json!({ "name": "net.fabricmc:intermediary:1.16.5", "url": server.url("/maven/") }),
+        ]));
+        let scheduler = DownloadScheduler::new().with_retry_policy(RetryPolicy::none());
+        let metadata = LibrariesMetadata::new(&paths).unwrap().push_mc_version(&version, &scheduler).await.unwrap();
+
+        let sizes: Vec<(&str, Option<u64>)> = metadata.libraries.iter().map(|library| (library.id.as_str(), library.size)).collect();
+        assert_eq!(sizes, Vec::from([("net.fabricmc:fabric-loader:0.11.3", Some(16)), ("net.fabricmc:intermediary:1.16.5", None)]));
+        assert!(server.requests().iter().all(|request| request.method == "HEAD"));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    fn natives_library(natives: serde_json::Value, classifiers: &[&str]) -> VersionLibrary {
+        let classifiers: serde_json::Map<String, serde_json::Value> = classifiers.iter().map(|classifier| (classifier.to_string(), serde_json::json!({ "path": classifier, "sha1": "", "size": 0, "url": "" }))).collect();
+        serde_json::from_value(serde_json::json!({ "name": "org.lwjgl:lwjgl:3.3.1", "natives": natives, "downloads": { "classifiers": classifiers } })).unwrap()
+    }
+
+    fn native_path(library: &VersionLibrary, os_name: &str, os_arch: &str) -> Option<String> {
+        let context = RuleContext::new(os_name, os_arch, "");
+        library.get_native_classifier(&context).and_then(|classifier| library.get_classifier(&classifier, &context)).map(|native| native.path.to_owned())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn natives_are_picked_for_every_platform() {
+        let lwjgl2 = natives_library(
+            serde_json::json!({ "windows": "natives-windows-${arch}", "linux": "natives-linux", "osx": "natives-osx" }),
+            &["natives-windows-32", "natives-windows-64", "natives-linux", "natives-osx"],
+        );
+        let lwjgl3 = natives_library(
+            serde_json::json!({ "windows": "natives-windows", "linux": "natives-linux", "osx": "natives-macos" }),
+            &["natives-windows", "natives-windows-arm64", "natives-linux", "natives-linux-arm64", "natives-macos", "natives-macos-arm64"],
+        );
+        let aarch_64 = natives_library(serde_json::json!({ "linux": "natives-linux", "osx": "natives-osx" }), &["natives-linux", "natives-linux-aarch_64", "natives-osx"]);
+        let table: Vec<(&str, &str, &VersionLibrary, Option<&str>)> = Vec::from([
+            ("windows", "x86", &lwjgl2, Some("natives-windows-32")),
+            ("windows", "amd64", &lwjgl2, Some("natives-windows-64")),
+            ("windows", "aarch64", &lwjgl2, Some("natives-windows-64")),
+            ("linux", "x86", &lwjgl2, Some("natives-linux")),
+            ("linux", "amd64", &lwjgl2, Some("natives-linux")),
+            ("linux", "aarch64", &lwjgl2, Some("natives-linux")),
+            ("osx", "x86_64", &lwjgl2, Some("natives-osx")),
+            ("osx", "aarch64", &lwjgl2, Some("natives-osx")),
+            ("windows", "x86", &lwjgl3, Some("natives-windows")),
+            ("windows", "amd64", &lwjgl3, Some("natives-windows")),
+            ("windows", "aarch64", &lwjgl3, Some("natives-windows-arm64")),
+            ("linux", "x86", &lwjgl3, Some("natives-linux")),
+            ("linux", "amd64", &lwjgl3, Some("natives-linux")),
+            ("linux", "aarch64", &lwjgl3, Some("natives-linux-arm64")),
+            ("osx", "x86_64", &lwjgl3, Some("natives-macos")),
+            ("osx", "aarch64", &lwjgl3, Some("natives-macos-arm64")),
+            ("windows", "amd64", &aarch_64, None),
+            ("linux", "amd64", &aarch_64, Some("natives-linux")),
+            ("linux", "aarch64", &aarch_64, Some("natives-linux-aarch_64")),
+            ("osx", "aarch64", &aarch_64, Some("natives-osx")),
+        ]);
+        for (os_name, os_arch, library, expected) in table {
+            assert_eq!(native_path(library, os_name, os_arch).as_deref(), expected, "{} {}", os_name, os_arch);
+        }
+    }
+
+    #[test]
+    fn libraries_without_a_natives_map_have_no_natives() {
+        let library = natives_library(serde_json::Value::Null, &["natives-linux"]);
+        assert_eq!(library.get_native_classifier(&RuleContext::new("linux", "amd64", "")), None);
+    }
+
+    #[test]
+    fn library_keys_drop_only_the_version() {
+        assert_eq!(library_key("org.ow2.asm:asm:9.1"), "org.ow2.asm:asm");
+        assert_eq!(library_key("org.lwjgl:lwjgl:3.2.2:natives-linux"), "org.lwjgl:lwjgl:natives-linux");
+        assert_eq!(library_key("org.lwjgl:lwjgl"), "org.lwjgl:lwjgl");
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::version::Version;
+
+pub const FABRIC_META: &str = "https://meta.fabricmc.net";
+pub const FABRIC_META_ENV: &str = "DUCK_LAUNCHER_FABRIC_META";
+pub const QUILT_META: &str = "https://meta.quiltmc.org";
+pub const QUILT_META_ENV: &str = "DUCK_LAUNCHER_QUILT_META";
+
+// Client for a Fabric-meta compatible endpoint. The base URL can point at a mirror or a local stand-in.
+// Quilt-meta serves the same documents under its v3 API, so both loaders share this client.
+#[derive(Debug, Clone)]
+pub struct FabricMeta {
+    base_url: String,
+    api: &'static str,
+    loader: &'static str,
+    env: &'static str,
+    stable_flag: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FabricLoaderListing {
+    pub loader: FabricLoaderVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FabricLoaderVersion {
+    pub version: String,
+    pub maven: String,
+    #[serde(default)]
+    pub stable: bool,
+}
+
+impl FabricMeta {
+    pub fn new() -> FabricMeta {
+        FabricMeta {
+            base_url: FABRIC_META.to_string(),
+            api: "v2",
+            loader: "fabric-loader",
+            env: FABRIC_META_ENV,
+            stable_flag: true,
+        }
+    }
+
+    // Quilt does not flag stable loaders, pre-releases carry a suffix such as 0.20.0-beta.1 instead.
+    pub fn quilt() -> FabricMeta {
+        FabricMeta {
+            base_url: QUILT_META.to_string(),
+            api: "v3",
+            loader: "quilt-loader",
+            env: QUILT_META_ENV,
+            stable_flag: false,
+        }
+    }
+
+    // The endpoint named by DUCK_LAUNCHER_FABRIC_META, or the official one.
+    pub fn from_env() -> FabricMeta {
+        FabricMeta::new().with_env()
+    }
+
+    // The endpoint named by DUCK_LAUNCHER_QUILT_META, or the official one.
+    pub fn quilt_from_env() -> FabricMeta {
+        FabricMeta::quilt().with_env()
+    }
+
+    fn with_env(self) -> FabricMeta {
+        match std::env::var(self.env) {
+            Ok(base_url) if !base_url.is_empty() => self.with_base_url(&base_url),
+            _ => self,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> FabricMeta {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Loader versions that support the given game version, newest first.
+    pub async fn get_loader_versions(&self, scheduler: &DownloadScheduler, game_version: &str) -> Result<Vec<FabricLoaderVersion>, LauncherError> {
+        let url = format!("{}/{}/versions/loader/{}", self.base_url, self.api, game_version);
+        let listings: Vec<FabricLoaderListing> = scheduler.client().get(&url).send().await?.error_for_status()?.json().await?;
+        Ok(listings.into_iter().map(|listing| listing.loader).collect())
+    }
+
+    pub async fn get_latest_loader_version(&self, scheduler: &DownloadScheduler, game_version: &str) -> Result<String, LauncherError> {
+        let versions = self.get_loader_versions(scheduler, game_version).await?;
+        match versions.iter().find(|version| self.is_stable(version)).or_else(|| versions.first()) {
+            Some(version) => Ok(version.version.to_owned()),
+            None => Err(LauncherError::VersionNotFound(format!("{} for {}", self.loader, game_version))),
+        }
+    }
+
+    fn is_stable(&self, version: &FabricLoaderVersion) -> bool {
+        if self.stable_flag {
+            version.stable
+        } else {
+            !version.version.contains('-')
+        }
+    }
+
+    pub fn version_id(&self, game_version: &str, loader_version: &str) -> String {
+        format!("{}-{}-{}", self.loader, loader_version, game_version)
+    }
+
+    /// Installs the launcher profile of a loader version as a version inheriting from the game
+    /// version and returns its id. Libraries are downloaded when the version itself is installed.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, game_version: &str, loader_version: &str) -> Result<String, LauncherError> {
+        let id = self.version_id(game_version, loader_version);
+        let path: PathBuf = Version::custom_path(paths, &id);
+        if !path.is_file() {
+            let url = format!("{}/{}/versions/loader/{}/{}/profile/json", self.base_url, self.api, game_version, loader_version);
+            scheduler.download(DownloadTask::new(&url, path)).await?;
+        }
+        Ok(id)
+    }
+
+    pub async fn get_version(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, game_version: &str, loader_version: &str) -> Result<Version, LauncherError> {
+        let id = self.install(paths, scheduler, game_version, loader_version).await?;
+        Version::get_version(paths, scheduler, &id).await
+    }
+}
+
+impl Default for FabricMeta {
+    fn default() -> Self {
+        FabricMeta::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_paths, TestServer};
+
+    const LOADERS: &str = r#"[
+        {"loader": {"separator": ".", "build": 4, "maven": "net.fabricmc:fabric-loader:0.12.0-beta.1", "version": "0.12.0-beta.1", "stable": false}},
+        {"loader": {"separator": ".", "build": 3, "maven": "net.fabricmc:fabric-loader:0.11.3", "version": "0.11.3", "stable": true}}
+    ]"#;
+    const PROFILE: &str = r#"{"id": "fabric-loader-0.11.3-1.16.5", "inheritsFrom": "1.16.5", "libraries": [], "mainClass": "net.fabricmc.loader.launch.knot.KnotClient"}"#;
+
+    fn server() -> TestServer {
+        TestServer::start(Vec::from([
+            ("/v2/versions/loader/1.16.5", LOADERS.as_bytes().to_vec()),
+            ("/v2/versions/loader/1.16.5/0.11.3/profile/json", PROFILE.as_bytes().to_vec()),
+            ("/v2/versions/loader/0.0.0", b"[]".to_vec()),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn lists_loader_versions() {
+        let server = server();
+        let fabric = FabricMeta::new().with_base_url(&server.url("/"));
+        let versions = fabric.get_loader_versions(&DownloadScheduler::new(), "1.16.5").await.unwrap();
+        assert_eq!(versions.iter().map(|version| version.version.as_str()).collect::<Vec<&str>>(), Vec::from(["0.12.0-beta.1", "0.11.3"]));
+    }
+
+    #[tokio::test]
+    async fn latest_loader_version_prefers_stable() {
+        let server = server();
+        let fabric = FabricMeta::new().with_base_url(&server.url(""));
+        let scheduler = DownloadScheduler::new();
+        assert_eq!(fabric.get_latest_loader_version(&scheduler, "1.16.5").await.unwrap(), "0.11.3");
+        assert!(matches!(fabric.get_latest_loader_version(&scheduler, "0.0.0").await, Err(LauncherError::VersionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn installs_the_profile_as_a_custom_version() {
+        let server = server();
+        let paths = temp_paths("fabric");
+        let fabric = FabricMeta::new().with_base_url(&server.url(""));
+        let id = fabric.install(&paths, &DownloadScheduler::new(), "1.16.5", "0.11.3").await.unwrap();
+        assert_eq!(id, "fabric-loader-0.11.3-1.16.5");
+        assert_eq!(std::fs::read_to_string(Version::custom_path(&paths, &id)).unwrap(), PROFILE);
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    const QUILT_LOADERS: &str = r#"[
+        {"loader": {"separator": ".", "build": 2, "maven": "org.quiltmc:quilt-loader:0.17.0-beta.2", "version": "0.17.0-beta.2"}},
+        {"loader": {"separator": ".", "build": 1, "maven": "org.quiltmc:quilt-loader:0.16.1", "version": "0.16.1"}}
+    ]"#;
+    const QUILT_PROFILE: &str = r#"{"id": "quilt-loader-0.16.1-1.18.2", "inheritsFrom": "1.18.2", "libraries": [], "mainClass": "org.quiltmc.loader.impl.launch.knot.KnotClient"}"#;
+
+    #[tokio::test]
+    async fn quilt_uses_v3_and_unsuffixed_versions_as_stable() {
+        let server = TestServer::start(Vec::from([
+            ("/v3/versions/loader/1.18.2", QUILT_LOADERS.as_bytes().to_vec()),
+            ("/v3/versions/loader/1.18.2/0.16.1/profile/json", QUILT_PROFILE.as_bytes().to_vec()),
+        ]));
+        let paths = temp_paths("quilt");
+        let quilt = FabricMeta::quilt().with_base_url(&server.url(""));
+        let scheduler = DownloadScheduler::new();
+        let loader_version = quilt.get_latest_loader_version(&scheduler, "1.18.2").await.unwrap();
+        assert_eq!(loader_version, "0.16.1");
+        let id = quilt.install(&paths, &scheduler, "1.18.2", &loader_version).await.unwrap();
+        assert_eq!(id, "quilt-loader-0.16.1-1.18.2");
+        assert_eq!(std::fs::read_to_string(Version::custom_path(&paths, &id)).unwrap(), QUILT_PROFILE);
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+}
@@ -0,0 +1,792 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::ZipArchive;
+
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::file_downloader;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::dependency::Dependency;
+use crate::minecraft::instance::InstanceType;
+use crate::minecraft::version::{Version, VersionLibrary};
+
+pub const FORGE_MAVEN: &str = "https://maven.minecraftforge.net";
+pub const FORGE_MAVEN_ENV: &str = "DUCK_LAUNCHER_FORGE_MAVEN";
+pub const FORGE_PROMOTIONS: &str = "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+pub const FORGE_PROMOTIONS_ENV: &str = "DUCK_LAUNCHER_FORGE_PROMOTIONS";
+const SERVER_ARGUMENTS_FILES: [&str; 2] = ["unix_args.txt", "win_args.txt"];
+
+// Where Forge installers and the recommended/latest promotions are fetched from.
+#[derive(Debug, Clone)]
+pub struct ForgeMeta {
+    maven_url: String,
+    promotions_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgePromotions {
+    pub promos: HashMap<String, String>,
+}
+
+// install_profile.json of an installer jar (spec 0 and later, Minecraft 1.13+).
+#[derive(Debug, Deserialize)]
+pub struct InstallProfile {
+    pub spec: Option<u32>,
+    pub minecraft: String,
+    pub json: String,
+    #[serde(default)]
+    pub data: HashMap<String, InstallProfileData>,
+    #[serde(default)]
+    pub processors: Vec<InstallProfileProcessor>,
+    #[serde(default)]
+    pub libraries: Vec<VersionLibrary>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallProfileData {
+    pub client: String,
+    pub server: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallProfileProcessor {
+    pub sides: Option<Vec<String>>,
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub outputs: Option<HashMap<String, String>>,
+}
+
+// meta/versions/<id>/<side>.installed: files under libraries/ the installer produced for the side,
+// such as patched jars, which no version JSON lists but the game needs.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InstallMarker {
+    pub files: Vec<String>,
+}
+
+impl ForgeMeta {
+    pub fn new() -> ForgeMeta {
+        ForgeMeta {
+            maven_url: FORGE_MAVEN.to_string(),
+            promotions_url: FORGE_PROMOTIONS.to_string(),
+        }
+    }
+
+    // The endpoints named by DUCK_LAUNCHER_FORGE_MAVEN and DUCK_LAUNCHER_FORGE_PROMOTIONS, or the official ones.
+    pub fn from_env() -> ForgeMeta {
+        let mut meta = ForgeMeta::new();
+        if let Ok(maven_url) = std::env::var(FORGE_MAVEN_ENV) {
+            if !maven_url.is_empty() {
+                meta = meta.with_maven_url(&maven_url);
+            }
+        }
+        if let Ok(promotions_url) = std::env::var(FORGE_PROMOTIONS_ENV) {
+            if !promotions_url.is_empty() {
+                meta = meta.with_promotions_url(&promotions_url);
+            }
+        }
+        meta
+    }
+
+    pub fn with_maven_url(mut self, maven_url: &str) -> ForgeMeta {
+        self.maven_url = maven_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_promotions_url(mut self, promotions_url: &str) -> ForgeMeta {
+        self.promotions_url = promotions_url.to_string();
+        self
+    }
+
+    /// The recommended Forge version for a game version, or the latest one if none is recommended.
+    pub async fn get_latest_version(&self, scheduler: &DownloadScheduler, game_version: &str) -> Result<String, LauncherError> {
+        let promotions: ForgePromotions = scheduler.client().get(&self.promotions_url).send().await?.error_for_status()?.json().await?;
+        let recommended = promotions.promos.get(&format!("{}-recommended", game_version));
+        match recommended.or_else(|| promotions.promos.get(&format!("{}-latest", game_version))) {
+            Some(version) => Ok(version.to_owned()),
+            None => Err(LauncherError::VersionNotFound(format!("forge for {}", game_version))),
+        }
+    }
+
+    pub fn version_id(game_version: &str, forge_version: &str) -> String {
+        format!("{}-forge-{}", game_version, forge_version)
+    }
+
+    pub async fn download_installer(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, game_version: &str, forge_version: &str) -> Result<PathBuf, LauncherError> {
+        let name = format!("net.minecraftforge:forge:{}-{}:installer", game_version, forge_version);
+        download_maven_artifact(paths, scheduler, &self.maven_url, &name).await
+    }
+
+    /// Downloads the installer of a Forge version and runs it for the given side with `java`.
+    /// Returns the installed version id.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, game_version: &str, forge_version: &str, side: InstanceType, java: &Path) -> Result<String, LauncherError> {
+        let installer = self.download_installer(paths, scheduler, game_version, forge_version).await?;
+        run_installer(paths, scheduler, &installer, side, java).await
+    }
+}
+
+impl Default for ForgeMeta {
+    fn default() -> Self {
+        ForgeMeta::new()
+    }
+}
+
+pub async fn download_maven_artifact(paths: &LauncherPaths, scheduler: &DownloadScheduler, maven_url: &str, name: &str) -> Result<PathBuf, LauncherError> {
+    let maven_path = match Dependency::parse(name) {
+        Some(dependency) => dependency.to_maven_url_layout_jar(),
+        None => return Err(LauncherError::InvalidLibrary(name.to_string())),
+    };
+    let url = format!("{}/{}", maven_url.trim_end_matches('/'), maven_path);
+    // Installers run arbitrary processors, so they are checked against the checksum the maven publishes.
+    let sha1_url = format!("{}.sha1", url);
+    let checksum = scheduler.client().get(&sha1_url).send().await?.error_for_status()?.text().await?;
+    let sha1 = match checksum.split_whitespace().next() {
+        Some(sha1) if sha1.len() == 40 && sha1.chars().all(|c| c.is_ascii_hexdigit()) => sha1.to_string(),
+        _ => return Err(LauncherError::Installer(format!("{} is not a SHA-1 checksum", sha1_url))),
+    };
+    let path: PathBuf = paths.library(&maven_path);
+    let task = DownloadTask::new(&url, path.to_owned()).with_sha1(&sha1);
+    if !scheduler.is_valid(&task) {
+        scheduler.download(task).await?;
+    }
+    Ok(path)
+}
+
+/// Whether the version JSON of `id` and the marker of the side are in place, so no installer has to run.
+pub fn is_installed(paths: &LauncherPaths, id: &str, side: InstanceType) -> bool {
+    Version::custom_path(paths, id).is_file() && is_side_installed(paths, id, side)
+}
+
+// Processors write a marker per side so a client install does not count as a server install.
+pub fn is_side_installed(paths: &LauncherPaths, id: &str, side: InstanceType) -> bool {
+    read_install_marker(paths, id, side).is_some()
+}
+
+fn install_marker_path(paths: &LauncherPaths, id: &str, side: InstanceType) -> PathBuf {
+    paths.join(Vec::from(["meta", "versions", id, &format!("{}.installed", side)]))
+}
+
+// A marker that cannot be read counts as missing, so the installer runs again.
+pub fn read_install_marker(paths: &LauncherPaths, id: &str, side: InstanceType) -> Option<InstallMarker> {
+    let data = std::fs::read(install_marker_path(paths, id, side)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// The arguments file a server install of `id` left behind for the running platform, if any.
+pub fn server_arguments_file(paths: &LauncherPaths, id: &str) -> Option<PathBuf> {
+    let name = if std::env::consts::OS.eq("windows") { "win_args.txt" } else { "unix_args.txt" };
+    let marker = read_install_marker(paths, id, InstanceType::SERVER)?;
+    marker.files.iter().find(|file| file.rsplit('/').next() == Some(name)).map(|file| paths.library(file))
+}
+
+/// The files under libraries/ that installing `id` for any side produced.
+pub fn installed_files(paths: &LauncherPaths, id: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for side in Vec::from([InstanceType::CLIENT, InstanceType::SERVER]).into_iter() {
+        if let Some(marker) = read_install_marker(paths, id, side) {
+            files.extend(marker.files.iter().map(|file| paths.library(file)));
+        }
+    }
+    files
+}
+
+/// Runs a Forge-style installer jar: writes its version JSON to meta/versions, downloads or extracts
+/// its libraries, and runs the processors of `side` with `java`. Returns the installed version id.
+pub async fn run_installer(paths: &LauncherPaths, scheduler: &DownloadScheduler, installer: &Path, side: InstanceType, java: &Path) -> Result<String, LauncherError> {
+    let mut archive = ZipArchive::new(File::open(installer)?)?;
+    let profile: InstallProfile = match read_entry(&mut archive, "install_profile.json")? {
+        Some(data) => {
+            let value: Value = serde_json::from_slice(&data)?;
+            if value.get("versionInfo").is_some() {
+                return Err(LauncherError::Installer(String::from("installers for Minecraft 1.12.2 and older are not supported")));
+            }
+            serde_json::from_value(value)?
+        }
+        None => return Err(LauncherError::Installer(format!("{} has no install_profile.json", installer.display()))),
+    };
+    log::debug!("Installing {} with spec {}", installer.display(), profile.spec.unwrap_or(0));
+
+    let version_json = match read_entry(&mut archive, profile.json.trim_start_matches('/'))? {
+        Some(data) => data,
+        None => return Err(LauncherError::Installer(format!("{} has no {}", installer.display(), profile.json))),
+    };
+    let version_value: Value = serde_json::from_slice(&version_json)?;
+    let id = match version_value.get("id").and_then(|id| id.as_str()) {
+        Some(id) => id.to_string(),
+        None => return Err(LauncherError::Installer(String::from("version.json has no id"))),
+    };
+    let version_libraries: Vec<VersionLibrary> = match version_value.get("libraries") {
+        Some(libraries) => serde_json::from_value(libraries.to_owned())?,
+        None => Vec::new(),
+    };
+
+    let minecraft = Version::get_version(paths, scheduler, &profile.minecraft).await?;
+    let mut tasks: Vec<DownloadTask> = Vec::new();
+    for library in profile.libraries.iter().chain(version_libraries.iter()) {
+        match library.get_artifact_task(paths) {
+            Some(task) => tasks.push(task),
+            None => extract_maven_artifact(&mut archive, paths, library)?,
+        }
+    }
+    scheduler.submit_invalid("forge libraries", tasks);
+    match side {
+        InstanceType::CLIENT => minecraft.install(paths, scheduler).await?,
+        InstanceType::SERVER => {
+            minecraft.verify_server(paths, scheduler)?;
+            let report = scheduler.run().await;
+            if !report.is_success() {
+                return Err(LauncherError::Download(report));
+            }
+        }
+    }
+
+    let temp: PathBuf = paths.join(Vec::from(["temp", "installers", &id]));
+    let data = resolve_data(&mut archive, paths, &profile, &minecraft, installer, side, &temp)?;
+    // Data entries name processor results too, like the patched game jar, without declaring them as outputs.
+    let mut outputs: Vec<PathBuf> = data.values().map(PathBuf::from).collect();
+    for processor in &profile.processors {
+        if let Some(sides) = &processor.sides {
+            if !sides.iter().any(|processor_side| processor_side.eq(&side.to_string())) {
+                continue;
+            }
+        }
+        run_processor(paths, processor, &data, java).await?;
+        outputs.extend(processor_outputs(paths, processor, &data)?.into_iter().map(|(path, _)| path));
+    }
+    if temp.exists() {
+        std::fs::remove_dir_all(&temp)?;
+    }
+    if side == InstanceType::SERVER {
+        // 1.17+ installers ship the server's launch arguments, which their run scripts read from next to
+        // the installer in libraries/.
+        for name in SERVER_ARGUMENTS_FILES.iter() {
+            if let Some(data) = read_entry(&mut archive, &format!("data/{}", name))? {
+                let path: PathBuf = installer.with_file_name(name);
+                std::fs::write(&path, data)?;
+                outputs.push(path);
+            }
+        }
+    }
+
+    let path: PathBuf = Version::custom_path(paths, &id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, &version_json)?;
+    let marker = InstallMarker {
+        files: library_files(paths, &outputs),
+    };
+    std::fs::write(install_marker_path(paths, &id, side), serde_json::to_string_pretty(&marker)?)?;
+    Ok(id)
+}
+
+// The existing files among `candidates` that live under libraries/, relative to it.
+fn library_files(paths: &LauncherPaths, candidates: &[PathBuf]) -> Vec<String> {
+    let root = paths.join(Vec::from(["libraries"]));
+    let mut files: BTreeSet<String> = BTreeSet::new();
+    for candidate in candidates {
+        if let Ok(relative) = candidate.strip_prefix(&root) {
+            if candidate.is_file() {
+                files.insert(relative.components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect::<Vec<String>>().join("/"));
+            }
+        }
+    }
+    files.into_iter().collect()
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<Vec<u8>>, LauncherError> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(LauncherError::Archive(e)),
+    };
+    let mut data: Vec<u8> = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(Some(data))
+}
+
+// Libraries without a download url ship in the installer's maven/ directory, or are produced by
+// a processor later on.
+fn extract_maven_artifact(archive: &mut ZipArchive<File>, paths: &LauncherPaths, library: &VersionLibrary) -> Result<(), LauncherError> {
+    let maven_path = match library.get_artifact_path() {
+        Some(maven_path) => maven_path,
+        None => match Dependency::from_version_library(library) {
+            Some(dependency) => dependency.to_maven_url_layout_jar(),
+            None => return Err(LauncherError::InvalidLibrary(library.name.to_owned())),
+        },
+    };
+    if let Some(data) = read_entry(archive, &format!("maven/{}", maven_path))? {
+        let path: PathBuf = paths.library(&maven_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, data)?;
+    }
+    Ok(())
+}
+
+// Values of `{KEY}` tokens: the built-in entries plus the profile's data for the side. Data values
+// are a maven coordinate in brackets, a literal in single quotes, or a file inside the installer.
+fn resolve_data(archive: &mut ZipArchive<File>, paths: &LauncherPaths, profile: &InstallProfile, minecraft: &Version, installer: &Path, side: InstanceType, temp: &Path) -> Result<HashMap<String, String>, LauncherError> {
+    let minecraft_jar = match side {
+        InstanceType::CLIENT => minecraft.client_path(paths),
+        InstanceType::SERVER => minecraft.server_path(paths),
+    };
+    let mut data: HashMap<String, String> = HashMap::new();
+    data.insert(String::from("SIDE"), side.to_string());
+    data.insert(String::from("MINECRAFT_JAR"), minecraft_jar.to_string_lossy().to_string());
+    data.insert(String::from("MINECRAFT_VERSION"), profile.minecraft.to_owned());
+    data.insert(String::from("ROOT"), paths.root().to_string_lossy().to_string());
+    data.insert(String::from("INSTALLER"), installer.to_string_lossy().to_string());
+    data.insert(String::from("LIBRARY_DIR"), paths.join(Vec::from(["libraries"])).to_string_lossy().to_string());
+    for (key, entry) in &profile.data {
+        let value = match side {
+            InstanceType::CLIENT => &entry.client,
+            InstanceType::SERVER => &entry.server,
+        };
+        let resolved = if value.starts_with('[') && value.ends_with(']') {
+            library_path(paths, &value[1..value.len() - 1])?.to_string_lossy().to_string()
+        } else if value.starts_with('\'') && value.ends_with('\'') && value.len() >= 2 {
+            value[1..value.len() - 1].to_string()
+        } else {
+            let entry_name = value.trim_start_matches('/');
+            let path: PathBuf = temp.join(entry_name);
+            match read_entry(archive, entry_name)? {
+                Some(bytes) => {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&path, bytes)?;
+                }
+                None => return Err(LauncherError::Installer(format!("{} is missing data entry {}", installer.display(), value))),
+            }
+            path.to_string_lossy().to_string()
+        };
+        data.insert(key.to_owned(), resolved);
+    }
+    Ok(data)
+}
+
+fn library_path(paths: &LauncherPaths, name: &str) -> Result<PathBuf, LauncherError> {
+    match Dependency::parse(name) {
+        Some(dependency) => Ok(paths.library(&dependency.to_maven_url_layout_jar())),
+        None => Err(LauncherError::InvalidLibrary(name.to_string())),
+    }
+}
+
+// Processor arguments and outputs: a maven coordinate in brackets becomes its library path,
+// anything else goes through replace_tokens.
+fn substitute(paths: &LauncherPaths, value: &str, data: &HashMap<String, String>) -> Result<String, LauncherError> {
+    if value.starts_with('[') && value.ends_with(']') {
+        Ok(library_path(paths, &value[1..value.len() - 1])?.to_string_lossy().to_string())
+    } else {
+        replace_tokens(value, data)
+    }
+}
+
+// The installer's token syntax: `{KEY}` is replaced by its data anywhere in the value, text in single
+// quotes is kept as is and a backslash escapes the next character.
+fn replace_tokens(value: &str, data: &HashMap<String, String>) -> Result<String, LauncherError> {
+    let mut result = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => result.push(escaped),
+                None => return Err(LauncherError::Installer(format!("{} ends with an escape", value))),
+            },
+            '{' => {
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => key.push(c),
+                        None => return Err(LauncherError::Installer(format!("unclosed token {{{} in {}", key, value))),
+                    }
+                }
+                match data.get(&key) {
+                    Some(resolved) => result.push_str(resolved),
+                    None => return Err(LauncherError::Installer(format!("unknown token {{{}}} in {}", key, value))),
+                }
+            }
+            '\'' => loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(c) => result.push(c),
+                    None => return Err(LauncherError::Installer(format!("unclosed quote in {}", value))),
+                }
+            },
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+// A processor is skipped when every output it declares already exists with the expected SHA-1.
+fn processor_outputs(paths: &LauncherPaths, processor: &InstallProfileProcessor, data: &HashMap<String, String>) -> Result<Vec<(PathBuf, String)>, LauncherError> {
+    let mut outputs: Vec<(PathBuf, String)> = Vec::new();
+    if let Some(declared) = &processor.outputs {
+        for (path, sha1) in declared {
+            outputs.push((PathBuf::from(substitute(paths, path, data)?), substitute(paths, sha1, data)?));
+        }
+    }
+    Ok(outputs)
+}
+
+fn outputs_valid(outputs: &[(PathBuf, String)]) -> Result<bool, LauncherError> {
+    for (path, sha1) in outputs {
+        if !path.is_file() {
+            return Ok(false);
+        }
+        if !file_downloader::sha1_file(path)?.eq_ignore_ascii_case(sha1) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+async fn run_processor(paths: &LauncherPaths, processor: &InstallProfileProcessor, data: &HashMap<String, String>, java: &Path) -> Result<(), LauncherError> {
+    let outputs = processor_outputs(paths, processor, data)?;
+    if !outputs.is_empty() && outputs_valid(&outputs)? {
+        log::debug!("Skipping processor {}, its outputs are up to date", processor.jar);
+        return Ok(());
+    }
+    let jar = library_path(paths, &processor.jar)?;
+    let main_class = match jar_main_class(&jar)? {
+        Some(main_class) => main_class,
+        None => return Err(LauncherError::Installer(format!("{} has no Main-Class", jar.display()))),
+    };
+    let mut classpath: Vec<String> = Vec::from([jar.to_string_lossy().to_string()]);
+    for library in &processor.classpath {
+        classpath.push(library_path(paths, library)?.to_string_lossy().to_string());
+    }
+    let mut arguments: Vec<String> = Vec::new();
+    for argument in &processor.args {
+        arguments.push(substitute(paths, argument, data)?);
+    }
+    let separator = if std::env::consts::OS.eq("windows") { ";" } else { ":" };
+    log::info!("Running processor {}", processor.jar);
+    let output = tokio::process::Command::new(java).arg("-cp").arg(classpath.join(separator)).arg(&main_class).args(&arguments).output().await;
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return Err(LauncherError::Installer(format!("unable to start {}: {}", java.display(), e))),
+    };
+    if !output.status.success() {
+        return Err(LauncherError::Installer(format!("processor {} exited with {}: {}", processor.jar, output.status, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    for (path, sha1) in &outputs {
+        let actual = file_downloader::sha1_file(path)?;
+        if !actual.eq_ignore_ascii_case(sha1) {
+            return Err(LauncherError::HashMismatch { url: path.to_string_lossy().to_string(), expected: sha1.to_owned(), actual });
+        }
+    }
+    Ok(())
+}
+
+fn jar_main_class(jar: &Path) -> Result<Option<String>, LauncherError> {
+    let mut archive = ZipArchive::new(File::open(jar)?)?;
+    let manifest = match read_entry(&mut archive, "META-INF/MANIFEST.MF")? {
+        Some(manifest) => String::from_utf8_lossy(&manifest).to_string(),
+        None => return Ok(None),
+    };
+    Ok(manifest.lines().find_map(|line| line.strip_prefix("Main-Class:")).map(|main_class| main_class.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_paths, TestServer};
+    use crate::common::retry_policy::RetryPolicy;
+    use sha1::{Digest, Sha1};
+    use std::io::Write;
+
+    const INSTALLER: &[u8] = b"installer";
+    const ID: &str = "1.17.1-forge-37.1.1";
+    const INSTALLER_PATH: &str = "/net/minecraftforge/forge/1.16.5-36.2.0/forge-1.16.5-36.2.0-installer.jar";
+
+    #[tokio::test]
+    async fn maven_artifacts_are_checked_against_the_published_sha1() {
+        let sha1 = format!("{:x}", Sha1::digest(INSTALLER));
+        let server = TestServer::start(Vec::from([(INSTALLER_PATH, INSTALLER.to_vec()), (&*format!("{}.sha1", INSTALLER_PATH), format!("{}  forge.jar\n", sha1).into_bytes())]));
+        let paths = temp_paths("forge");
+        let path = download_maven_artifact(&paths, &DownloadScheduler::new(), &server.url(""), "net.minecraftforge:forge:1.16.5-36.2.0:installer").await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), INSTALLER);
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn maven_artifacts_with_a_wrong_sha1_are_refused() {
+        let server = TestServer::start(Vec::from([(INSTALLER_PATH, INSTALLER.to_vec()), (&*format!("{}.sha1", INSTALLER_PATH), b"da39a3ee5e6b4b0d3255bfef95601890afd80709".to_vec())]));
+        let paths = temp_paths("forge");
+        let scheduler = DownloadScheduler::new().with_retry_policy(RetryPolicy::none());
+        let result = download_maven_artifact(&paths, &scheduler, &server.url(""), "net.minecraftforge:forge:1.16.5-36.2.0:installer").await;
+        assert!(matches!(result, Err(LauncherError::HashMismatch { .. })));
+        let _ = std::fs::remove_dir_all(paths.root());
+    }
+
+    #[test]
+    fn library_gc_keeps_files_listed_in_install_markers() {
+        let paths = temp_paths("forge");
+        let patched = paths.library("net/minecraftforge/forge/1.16.5-36.2.0/forge-1.16.5-36.2.0-client.jar");
+        let stray = paths.library("org/example/stray/1.0/stray-1.0.jar");
+        for path in Vec::from([&patched, &stray]).into_iter() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"jar").unwrap();
+        }
+        let id = "1.16.5-forge-36.2.0";
+        std::fs::create_dir_all(install_marker_path(&paths, id, InstanceType::CLIENT).parent().unwrap()).unwrap();
+        let marker = InstallMarker {
+            files: library_files(&paths, &[patched.to_owned(), stray.with_file_name("missing.jar"), paths.join(Vec::from(["temp", "data.lzma"]))]),
+        };
+        assert_eq!(marker.files, Vec::from(["net/minecraftforge/forge/1.16.5-36.2.0/forge-1.16.5-36.2.0-client.jar"]));
+        std::fs::write(install_marker_path(&paths, id, InstanceType::CLIENT), serde_json::to_string(&marker).unwrap()).unwrap();
+        assert!(is_side_installed(&paths, id, InstanceType::CLIENT));
+        assert!(!is_side_installed(&paths, id, InstanceType::SERVER));
+
+        let version: Version = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "arguments": {"game": [], "jvm": []},
+            "assetIndex": {"id": "1.16", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "assets": "1.16",
+            "downloads": {},
+            "libraries": [],
+            "mainClass": "cpw.mods.modlauncher.Launcher",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2021-01-14T16:05:32+00:00",
+            "time": "2021-01-14T16:05:32+00:00",
+            "type": "release"
+        }))
+        .unwrap();
+        let garbage = crate::minecraft::dependency::collect_garbage(&paths, &[version], true).unwrap();
+        assert_eq!(garbage, Vec::from([stray]));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[test]
+    fn empty_install_markers_count_as_missing() {
+        let paths = temp_paths("forge");
+        let marker = install_marker_path(&paths, "1.16.5-forge-36.2.0", InstanceType::CLIENT);
+        std::fs::create_dir_all(marker.parent().unwrap()).unwrap();
+        File::create(&marker).unwrap();
+        assert!(!is_side_installed(&paths, "1.16.5-forge-36.2.0", InstanceType::CLIENT));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    fn sha1(data: &[u8]) -> String {
+        format!("{:x}", Sha1::digest(data))
+    }
+
+    fn zip(path: &Path, entries: Vec<(&str, Vec<u8>)>) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    // A shell script standing in for java: logs its arguments to java.log next to itself and writes
+    // "patched" to the file named by its last argument.
+    #[cfg(unix)]
+    fn stub_java(paths: &LauncherPaths) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let java = paths.join(Vec::from(["bin", "java"]));
+        std::fs::create_dir_all(java.parent().unwrap()).unwrap();
+        std::fs::write(&java, "#!/bin/sh\nprintf '%s\\n' \"$@\" >> \"$(dirname \"$0\")/java.log\"\nfor last; do :; done\nmkdir -p \"$(dirname \"$last\")\"\nprintf patched > \"$last\"\n").unwrap();
+        std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+        java
+    }
+
+    fn java_log(paths: &LauncherPaths) -> Vec<String> {
+        std::fs::read_to_string(paths.join(Vec::from(["bin", "java.log"]))).unwrap_or_default().lines().map(String::from).collect()
+    }
+
+    // The vanilla 1.17.1 JSON under meta/ with its jars and asset index served by `server`.
+    fn vanilla(paths: &LauncherPaths, server: &TestServer) {
+        let path = Version::vanilla_path(paths, "1.17.1");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let version = serde_json::json!({
+            "id": "1.17.1",
+            "arguments": {"game": [], "jvm": []},
+            "assetIndex": {"id": "1.17", "sha1": sha1(b"{\"objects\": {}}"), "size": 15, "totalSize": 0, "url": server.url("/index.json")},
+            "assets": "1.17",
+            "downloads": {
+                "client": {"sha1": sha1(b"client"), "size": 6, "url": server.url("/client.jar")},
+                "server": {"sha1": sha1(b"server"), "size": 6, "url": server.url("/server.jar")}
+            },
+            "libraries": [],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2021-07-06T12:01:34+00:00",
+            "time": "2021-07-06T12:01:34+00:00",
+            "type": "release"
+        });
+        std::fs::write(path, version.to_string()).unwrap();
+    }
+
+    fn vanilla_server() -> TestServer {
+        TestServer::start(Vec::from([("/index.json", b"{\"objects\": {}}".to_vec()), ("/client.jar", b"client".to_vec()), ("/server.jar", b"server".to_vec())]))
+    }
+
+    // An installer whose processors run on the stub java, one per side. The client processor's output
+    // is declared with `patched_sha1`.
+    fn installer(paths: &LauncherPaths, patched_sha1: &str) -> PathBuf {
+        let profile = serde_json::json!({
+            "spec": 1,
+            "minecraft": "1.17.1",
+            "json": "/version.json",
+            "data": {
+                "MAPPINGS": {"client": "[net.example:mappings:1.0:mappings@txt]", "server": "[net.example:mappings:1.0:mappings@txt]"},
+                "BINPATCH": {"client": "/data/client.lzma", "server": "/data/server.lzma"},
+                "LABEL": {"client": "'client build'", "server": "'server build'"},
+                "PATCHED": {"client": "[net.example:patched:1.0:client]", "server": "[net.example:patched:1.0:server]"},
+                "PATCHED_SHA": {"client": format!("'{}'", patched_sha1), "server": format!("'{}'", sha1(b"patched"))}
+            },
+            "processors": [
+                {
+                    "sides": ["client"],
+                    "jar": "net.example:processor:1.0",
+                    "args": ["--jar", "{MINECRAFT_JAR}", "--side", "{SIDE}", "--mappings", "{MAPPINGS}", "--patch", "{BINPATCH}", "--label", "{LABEL}", "--root={ROOT}/run", "--quoted", "'{SIDE}'", "--library", "[net.example:extra:1.0]", "--output", "{PATCHED}"],
+                    "outputs": {"{PATCHED}": "{PATCHED_SHA}"}
+                },
+                {
+                    "sides": ["server"],
+                    "jar": "net.example:processor:1.0",
+                    "args": ["--jar", "{MINECRAFT_JAR}", "--side", "{SIDE}", "--output", "{PATCHED}"],
+                    "outputs": {"{PATCHED}": "{PATCHED_SHA}"}
+                }
+            ],
+            "libraries": [{"name": "net.example:processor:1.0"}]
+        });
+        let version = serde_json::json!({
+            "id": ID,
+            "inheritsFrom": "1.17.1",
+            "arguments": {"game": [], "jvm": []},
+            "libraries": [],
+            "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+            "releaseTime": "2021-12-01T00:00:00+00:00",
+            "time": "2021-12-01T00:00:00+00:00",
+            "type": "release"
+        });
+        let processor = paths.join(Vec::from(["processor.jar"]));
+        zip(&processor, Vec::from([("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\nMain-Class: net.example.Processor\r\n".to_vec())]));
+        let path = paths.library("net/minecraftforge/forge/1.17.1-37.1.1/forge-1.17.1-37.1.1-installer.jar");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        zip(&path, Vec::from([
+            ("install_profile.json", profile.to_string().into_bytes()),
+            ("version.json", version.to_string().into_bytes()),
+            ("data/client.lzma", b"client patches".to_vec()),
+            ("data/server.lzma", b"server patches".to_vec()),
+            ("data/unix_args.txt", b"-p libraries/cpw/mods/bootstraplauncher/1.0.0/bootstraplauncher-1.0.0.jar\ncpw.mods.bootstraplauncher.BootstrapLauncher\n".to_vec()),
+            ("data/win_args.txt", b"-p libraries/cpw/mods/bootstraplauncher/1.0.0/bootstraplauncher-1.0.0.jar\r\ncpw.mods.bootstraplauncher.BootstrapLauncher\r\n".to_vec()),
+            ("maven/net/example/processor/1.0/processor-1.0.jar", std::fs::read(&processor).unwrap()),
+        ]));
+        path
+    }
+
+    #[test]
+    fn tokens_are_replaced_inside_larger_values() {
+        let data: HashMap<String, String> = HashMap::from([(String::from("ROOT"), String::from("/games")), (String::from("SIDE"), String::from("client"))]);
+        assert_eq!(replace_tokens("--root={ROOT}/run", &data).unwrap(), "--root=/games/run");
+        assert_eq!(replace_tokens("{SIDE}-{SIDE}", &data).unwrap(), "client-client");
+        assert_eq!(replace_tokens("'{SIDE}' is {SIDE}", &data).unwrap(), "{SIDE} is client");
+        assert_eq!(replace_tokens("\\{SIDE\\}", &data).unwrap(), "{SIDE}");
+        assert_eq!(replace_tokens("plain", &data).unwrap(), "plain");
+        let error = replace_tokens("--out={OUTPUT}", &data).unwrap_err().to_string();
+        assert!(error.contains("{OUTPUT}"), "{}", error);
+        assert!(replace_tokens("{SIDE", &data).is_err());
+        assert!(replace_tokens("'open", &data).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn processors_of_the_side_run_with_substituted_arguments() {
+        let server = vanilla_server();
+        let paths = temp_paths("forge");
+        vanilla(&paths, &server);
+        let java = stub_java(&paths);
+        let installer = installer(&paths, &sha1(b"patched"));
+
+        let id = run_installer(&paths, &DownloadScheduler::new(), &installer, InstanceType::CLIENT, &java).await.unwrap();
+        assert_eq!(id, ID);
+        let processor = paths.library("net/example/processor/1.0/processor-1.0.jar");
+        let client_patched = paths.library("net/example/patched/1.0/patched-1.0-client.jar");
+        let expected: Vec<String> = Vec::from([
+            "-cp",
+            &processor.to_string_lossy(),
+            "net.example.Processor",
+            "--jar",
+            &paths.library("com/mojang/minecraft/1.17.1/client/1.17.1.jar").to_string_lossy(),
+            "--side",
+            "client",
+            "--mappings",
+            &paths.library("net/example/mappings/1.0/mappings-1.0-mappings.txt").to_string_lossy(),
+            "--patch",
+            &paths.join(Vec::from(["temp", "installers", ID, "data", "client.lzma"])).to_string_lossy(),
+            "--label",
+            "client build",
+            &format!("--root={}/run", paths.root().to_string_lossy()),
+            "--quoted",
+            "{SIDE}",
+            "--library",
+            &paths.library("net/example/extra/1.0/extra-1.0.jar").to_string_lossy(),
+            "--output",
+            &client_patched.to_string_lossy(),
+        ])
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(java_log(&paths), expected);
+        assert!(Version::custom_path(&paths, ID).is_file());
+        assert!(is_installed(&paths, ID, InstanceType::CLIENT));
+        assert!(!is_installed(&paths, ID, InstanceType::SERVER));
+        assert!(read_install_marker(&paths, ID, InstanceType::CLIENT).unwrap().files.contains(&String::from("net/example/patched/1.0/patched-1.0-client.jar")));
+        assert!(!paths.join(Vec::from(["temp", "installers", ID])).exists());
+        assert_eq!(server_arguments_file(&paths, ID), None);
+
+        run_installer(&paths, &DownloadScheduler::new(), &installer, InstanceType::SERVER, &java).await.unwrap();
+        let server_run = java_log(&paths)[expected.len()..].to_vec();
+        assert_eq!(server_run[3..], [
+            String::from("--jar"),
+            paths.library("com/mojang/minecraft/1.17.1/server/1.17.1.jar").to_string_lossy().to_string(),
+            String::from("--side"),
+            String::from("server"),
+            String::from("--output"),
+            paths.library("net/example/patched/1.0/patched-1.0-server.jar").to_string_lossy().to_string(),
+        ]);
+        assert!(is_installed(&paths, ID, InstanceType::SERVER));
+        let arguments_file = server_arguments_file(&paths, ID).unwrap();
+        assert_eq!(arguments_file.parent(), installer.parent());
+        assert!(std::fs::read_to_string(arguments_file).unwrap().contains("cpw.mods.bootstraplauncher.BootstrapLauncher"));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn processor_outputs_with_a_wrong_sha1_fail_the_install() {
+        let server = vanilla_server();
+        let paths = temp_paths("forge");
+        vanilla(&paths, &server);
+        let java = stub_java(&paths);
+        let installer = installer(&paths, "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+
+        let result = run_installer(&paths, &DownloadScheduler::new(), &installer, InstanceType::CLIENT, &java).await;
+        match result {
+            Err(LauncherError::HashMismatch { url, expected, actual }) => {
+                assert_eq!(url, paths.library("net/example/patched/1.0/patched-1.0-client.jar").to_string_lossy());
+                assert_eq!(expected, "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+                assert_eq!(actual, sha1(b"patched"));
+            }
+            result => panic!("expected a hash mismatch, got {:?}", result),
+        }
+        assert!(!is_side_installed(&paths, ID, InstanceType::CLIENT));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+}
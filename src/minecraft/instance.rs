@@ -0,0 +1,543 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::common::download_scheduler::DownloadScheduler;
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft;
+use crate::minecraft::account::Accounts;
+use crate::minecraft::fabric::FabricMeta;
+use crate::minecraft::forge;
+use crate::minecraft::forge::ForgeMeta;
+use crate::minecraft::java;
+use crate::minecraft::runtime;
+use crate::minecraft::runtime::RuntimeMeta;
+use crate::minecraft::neoforge::NeoForgeMeta;
+use crate::minecraft::process::GameProcess;
+use crate::minecraft::version::Version;
+use crate::minecraft::{LaunchOptions, ServerOptions};
+
+// Schema version written to instance.json, bump it and extend `Instance::migrate` when the format changes.
+pub const INSTANCE_FORMAT_VERSION: u8 = 1;
+const INSTANCE_FILE: &str = "instance.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceType{
+    CLIENT,
+    SERVER,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceFlavor{
+    FABRIC,
+    FORGE,
+    NEOFORGE,
+    QUILT,
+    VANILLA
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Instance{
+    version: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    name: String,
+    game_version: String,
+    r#type: InstanceType,
+    flavor: InstanceFlavor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    loader_version: Option<String>,
+    selected_account: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    jvm_arguments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    java_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    server_properties: Option<BTreeMap<String, String>>,
+}
+
+impl Instance{
+    pub fn new(name: &str, game_version: &str, selected_account: &str) -> Instance{
+        Instance{
+            version: INSTANCE_FORMAT_VERSION,
+            tags: None,
+            name: name.to_string(),
+            game_version: game_version.to_string(),
+            r#type: InstanceType::CLIENT,
+            flavor: InstanceFlavor:: VANILLA,
+            loader_version: None,
+            selected_account: selected_account.to_string(),
+            jvm_arguments: None,
+            java_path: None,
+            server_properties: None
+        }
+    }
+
+    pub fn version(&self) -> u8{
+        self.version
+    }
+
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    pub fn game_version(&self) -> &str{
+        &self.game_version
+    }
+
+    pub fn tags(&self) -> Option<&Vec<String>>{
+        self.tags.as_ref()
+    }
+
+    pub fn r#type(&self) -> &InstanceType{
+        &self.r#type
+    }
+
+    pub fn flavor(&self) -> &InstanceFlavor{
+        &self.flavor
+    }
+
+    pub fn loader_version(&self) -> Option<&String>{
+        self.loader_version.as_ref()
+    }
+
+    pub fn selected_account(&self) -> &str{
+        &self.selected_account
+    }
+
+    pub fn jvm_arguments(&self) -> Option<&String>{
+        self.jvm_arguments.as_ref()
+    }
+
+    pub fn java_path(&self) -> Option<&PathBuf>{
+        self.java_path.as_ref()
+    }
+
+    pub fn server_properties(&self) -> Option<&BTreeMap<String, String>>{
+        self.server_properties.as_ref()
+    }
+
+    pub fn set_server_property(&mut self, key: String, value: String){
+        self.server_properties.get_or_insert_with(BTreeMap::new).insert(key, value);
+    }
+
+    pub fn add_tag(&mut self, tag: String){
+        match &mut self.tags {
+            Some(tags) => {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            },
+            None => {
+                let tags: Vec<String> = Vec::from([tag]);
+                self.tags = Some(tags);
+            }
+        }
+    }
+
+    pub fn with_type(mut self, r#type: InstanceType) -> Instance{
+        self.r#type = r#type;
+        self
+    }
+
+    pub fn with_flavor(mut self, flavor: InstanceFlavor) -> Instance{
+        self.flavor = flavor;
+        self
+    }
+
+    pub fn with_loader_version(mut self, loader_version: String) -> Instance{
+        self.loader_version = Some(loader_version);
+        self
+    }
+
+    pub fn with_account(mut self, selected_account: String) -> Instance{
+        self.selected_account = selected_account;
+        self
+    }
+
+    pub fn with_jvm_arguments(mut self, jvm_arguments: String) -> Instance{
+        self.jvm_arguments = Some(jvm_arguments);
+        self
+    }
+
+    // Overrides the runtime picked from the discovered Java installations.
+    pub fn with_java_path(mut self, java_path: PathBuf) -> Instance{
+        self.java_path = Some(java_path);
+        self
+    }
+
+    pub fn with_game_version(mut self, game_version: String) -> Instance{
+        self.game_version = game_version;
+        self
+    }
+
+    pub fn directory(&self, paths: &LauncherPaths) -> PathBuf {
+        paths.join(Vec::from(["instances", &self.name]))
+    }
+
+    pub fn game_directory(&self, paths: &LauncherPaths) -> PathBuf {
+        paths.join(Vec::from(["instances", &self.name, ".minecraft"]))
+    }
+
+    pub fn natives_directory(&self, paths: &LauncherPaths) -> PathBuf {
+        paths.join(Vec::from(["instances", &self.name, "natives"]))
+    }
+
+    // Resolves the version this instance actually launches, which for modded flavors is the loader
+    // profile rather than the plain Minecraft version.
+    pub async fn resolve_version(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<Version, LauncherError> {
+        match self.flavor {
+            InstanceFlavor::VANILLA => Version::get_version(paths, scheduler, &self.game_version).await,
+            InstanceFlavor::FABRIC | InstanceFlavor::QUILT => {
+                let meta = if self.flavor == InstanceFlavor::FABRIC { FabricMeta::from_env() } else { FabricMeta::quilt_from_env() };
+                let loader_version = match &self.loader_version {
+                    Some(loader_version) => loader_version.to_owned(),
+                    None => meta.get_latest_loader_version(scheduler, &self.game_version).await?,
+                };
+                meta.get_version(paths, scheduler, &self.game_version, &loader_version).await
+            }
+            InstanceFlavor::FORGE => {
+                let forge = ForgeMeta::from_env();
+                let forge_version = match &self.loader_version {
+                    Some(forge_version) => forge_version.to_owned(),
+                    None => forge.get_latest_version(scheduler, &self.game_version).await?,
+                };
+                let mut id = ForgeMeta::version_id(&self.game_version, &forge_version);
+                if !forge::is_installed(paths, &id, self.r#type) {
+                    // The installer runs once per side, so the Java its processors need is only looked up then.
+                    let java = self.resolve_installer_java(paths, scheduler).await?;
+                    id = forge.install(paths, scheduler, &self.game_version, &forge_version, self.r#type, &java).await?;
+                }
+                Version::get_version(paths, scheduler, &id).await
+            }
+            InstanceFlavor::NEOFORGE => {
+                let neoforge = NeoForgeMeta::from_env();
+                let neoforge_version = match &self.loader_version {
+                    Some(neoforge_version) => neoforge_version.to_owned(),
+                    None => neoforge.get_latest_version(scheduler, &self.game_version).await?,
+                };
+                let mut id = NeoForgeMeta::version_id(&neoforge_version);
+                if !forge::is_installed(paths, &id, self.r#type) {
+                    // The installer runs once per side, so the Java its processors need is only looked up then.
+                    let java = self.resolve_installer_java(paths, scheduler).await?;
+                    id = neoforge.install(paths, scheduler, &neoforge_version, self.r#type, &java).await?;
+                }
+                Version::get_version(paths, scheduler, &id).await
+            }
+        }
+    }
+
+    /// The Java executable the instance runs `version` with: its Java path if set, otherwise a
+    /// discovered runtime matching the version's javaVersion. When none is found the runtime component
+    /// of the version is installed under runtime/.
+    pub async fn resolve_java(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, version: &Version) -> Result<PathBuf, LauncherError> {
+        match java::resolve(paths, self.java_path.as_deref(), version.get_java_major_version()) {
+            Ok(java) => Ok(java),
+            Err(e) if self.java_path.is_none() => {
+                let component = version.java_version.as_ref().map(|java_version| java_version.component.as_str()).unwrap_or(runtime::LEGACY_COMPONENT);
+                log::info!("{}, installing the {} runtime", e, component);
+                RuntimeMeta::from_env().install(paths, scheduler, component).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Installer processors run before the modded version exists, on the Java of the game version.
+    async fn resolve_installer_java(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<PathBuf, LauncherError> {
+        let version = Version::get_version(paths, scheduler, &self.game_version).await?;
+        self.resolve_java(paths, scheduler, &version).await
+    }
+
+    /// Resolves the version of the instance and installs what its side needs: the client with its
+    /// assets and libraries, or the dedicated server jar.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<Version, LauncherError> {
+        let version = self.resolve_version(paths, scheduler).await?;
+        match self.r#type {
+            InstanceType::CLIENT => version.install(paths, scheduler).await?,
+            InstanceType::SERVER => version.install_server(paths, scheduler).await?,
+        }
+        Ok(version)
+    }
+
+    /// Installs whatever the instance is missing and launches it with its own game directory, natives
+    /// directory and JVM arguments. `account` overrides the instance's selected account. Returns once
+    /// the game has started.
+    pub async fn launch(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, account: Option<&str>) -> Result<GameProcess, LauncherError> {
+        if self.r#type != InstanceType::CLIENT {
+            return Err(LauncherError::Launch(format!("{} is a {} instance", self.name, self.r#type)));
+        }
+        let accounts = Accounts::load(paths)?;
+        let username = account.or_else(|| Some(self.selected_account.as_str()).filter(|username| !username.is_empty()));
+        let account = accounts.resolve(username)?;
+        let version = self.install(paths, scheduler).await?;
+        let options = LaunchOptions::new(self.game_directory(paths), self.natives_directory(paths))
+            .with_java(self.resolve_java(paths, scheduler, &version).await?)
+            .with_jvm_arguments(self.split_jvm_arguments());
+        minecraft::launch_client(paths, account, &version, &options)
+    }
+
+    /// Installs the server jar if needed, writes server.properties from the instance's server properties
+    /// and runs the server in the game directory. `accept_eula` records the user's agreement to the EULA.
+    pub async fn launch_server(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, accept_eula: bool) -> Result<GameProcess, LauncherError> {
+        if self.r#type != InstanceType::SERVER {
+            return Err(LauncherError::Launch(format!("{} is a {} instance", self.name, self.r#type)));
+        }
+        if self.flavor == InstanceFlavor::FABRIC || self.flavor == InstanceFlavor::QUILT {
+            return Err(LauncherError::Launch(format!("{} server instances are not supported yet", self.flavor)));
+        }
+        let version = self.install(paths, scheduler).await?;
+        let mut options = ServerOptions::new(self.game_directory(paths))
+            .with_java(self.resolve_java(paths, scheduler, &version).await?)
+            .with_jvm_arguments(self.split_jvm_arguments())
+            .with_properties(self.server_properties.to_owned().unwrap_or_default())
+            .with_accept_eula(accept_eula);
+        if self.flavor != InstanceFlavor::VANILLA {
+            match forge::server_arguments_file(paths, &version.id) {
+                Some(arguments_file) => options = options.with_arguments_file(arguments_file),
+                None => return Err(LauncherError::Launch(format!("{} has no server arguments file, only {} 1.17 and newer servers can be launched", version.id, self.flavor))),
+            }
+        }
+        minecraft::launch_server(paths, &version, &options)
+    }
+
+    fn split_jvm_arguments(&self) -> Vec<String> {
+        match &self.jvm_arguments {
+            Some(jvm_arguments) => jvm_arguments.split_whitespace().map(String::from).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn exists(paths: &LauncherPaths, name: &str) -> bool {
+        paths.join(Vec::from(["instances", name, INSTANCE_FILE])).is_file()
+    }
+
+    pub fn load(paths: &LauncherPaths, name: &str) -> Result<Instance, LauncherError> {
+        Instance::validate_name(name)?;
+        let path: PathBuf = paths.join(Vec::from(["instances", name, INSTANCE_FILE]));
+        if !path.is_file() {
+            return Err(LauncherError::InstanceNotFound(name.to_string()));
+        }
+        let mut file = File::open(path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        let value: Value = serde_json::from_str(&data)?;
+        let instance: Instance = serde_json::from_value(Instance::migrate(name, value)?)?;
+        Ok(instance)
+    }
+
+    pub fn save(&self, paths: &LauncherPaths) -> Result<(), LauncherError> {
+        Instance::validate_name(&self.name)?;
+        let directory = self.directory(paths);
+        std::fs::create_dir_all(&directory)?;
+        let file = File::create(directory.join(INSTANCE_FILE))?;
+        serde_json::to_writer_pretty(file, &self)?;
+        Ok(())
+    }
+
+    pub fn list(paths: &LauncherPaths) -> Result<Vec<Instance>, LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["instances"]));
+        let mut instances: Vec<Instance> = Vec::new();
+        if !path.exists() {
+            return Ok(instances);
+        }
+        for entry in std::fs::read_dir(path)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if !Instance::exists(paths, &name) {
+                continue;
+            }
+            match Instance::load(paths, &name) {
+                Ok(instance) => instances.push(instance),
+                Err(e) => log::warn!("Skipping instance {}: {}", name, e),
+            }
+        }
+        instances.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(instances)
+    }
+
+    pub fn delete(paths: &LauncherPaths, name: &str) -> Result<(), LauncherError> {
+        Instance::validate_name(name)?;
+        if !Instance::exists(paths, name) {
+            return Err(LauncherError::InstanceNotFound(name.to_string()));
+        }
+        std::fs::remove_dir_all(paths.join(Vec::from(["instances", name])))?;
+        Ok(())
+    }
+
+    // Instance names become directory names, so anything that could escape instances/ is rejected.
+    fn validate_name(name: &str) -> Result<(), LauncherError> {
+        if name.is_empty() || name.eq(".") || name.eq("..") || name.contains(['/', '\\']) {
+            return Err(LauncherError::InvalidInstance(format!("{} is not a valid instance name", name)));
+        }
+        Ok(())
+    }
+
+    // Upgrades an instance.json of an older format version to the current one in place.
+    fn migrate(name: &str, value: Value) -> Result<Value, LauncherError> {
+        let version = match value.get("version") {
+            Some(version) => version.as_u64().ok_or_else(|| LauncherError::InvalidInstance(format!("{} has an invalid format version {}", name, version)))?,
+            None => 0,
+        };
+        match version {
+            0 => Instance::migrate(name, Instance::migrate_v0(name, value)?),
+            1 => Ok(value),
+            v => Err(LauncherError::InvalidInstance(format!("{} uses format version {}, this launcher supports up to {}", name, v, INSTANCE_FORMAT_VERSION))),
+        }
+    }
+
+    // Files written before the format was versioned were keyed by the Minecraft version they play and
+    // only stored what differed from a vanilla client, so the missing fields are filled in from that.
+    fn migrate_v0(name: &str, mut value: Value) -> Result<Value, LauncherError> {
+        let object = match value.as_object_mut() {
+            Some(object) => object,
+            None => return Err(LauncherError::InvalidInstance(format!("{} is not an instance object", name))),
+        };
+        object.insert("version".to_string(), Value::from(1));
+        object.entry("name").or_insert_with(|| Value::from(name));
+        object.entry("gameVersion").or_insert_with(|| Value::from(name));
+        object.entry("type").or_insert_with(|| Value::from(InstanceType::CLIENT.to_string()));
+        object.entry("flavor").or_insert_with(|| Value::from(InstanceFlavor::VANILLA.to_string()));
+        object.entry("selectedAccount").or_insert_with(|| Value::from(""));
+        Ok(value)
+    }
+}
+
+impl FromStr for InstanceFlavor{
+    type Err = LauncherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fabric" => Ok(InstanceFlavor::FABRIC),
+            "forge" => Ok(InstanceFlavor::FORGE),
+            "neoforge" => Ok(InstanceFlavor::NEOFORGE),
+            "quilt" => Ok(InstanceFlavor::QUILT),
+            "vanilla" => Ok(InstanceFlavor::VANILLA),
+            _ => Err(LauncherError::InvalidInstance(format!("Unknown flavor {}", s))),
+        }
+    }
+}
+
+impl FromStr for InstanceType{
+    type Err = LauncherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "client" => Ok(InstanceType::CLIENT),
+            "server" => Ok(InstanceType::SERVER),
+            _ => Err(LauncherError::InvalidInstance(format!("Unknown instance type {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for InstanceFlavor{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceFlavor::FABRIC => write!(f, "fabric"),
+            InstanceFlavor::FORGE => write!(f, "forge"),
+            InstanceFlavor::NEOFORGE => write!(f, "neoforge"),
+            InstanceFlavor::QUILT => write!(f, "quilt"),
+            InstanceFlavor::VANILLA => write!(f, "vanilla"),
+        }
+    }
+}
+
+impl fmt::Display for InstanceType{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceType::CLIENT => write!(f, "client"),
+            InstanceType::SERVER => write!(f, "server"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::temp_paths;
+
+    fn write_instance(paths: &LauncherPaths, name: &str, data: &str) {
+        let directory = paths.join(Vec::from(["instances", name]));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join(INSTANCE_FILE), data).unwrap();
+    }
+
+    #[test]
+    fn instances_round_trip_through_the_data_directory() {
+        let paths = temp_paths("instance-round-trip");
+        let mut survival = Instance::new("survival", "1.16.5", "duck@example.com").with_flavor(InstanceFlavor::FABRIC).with_jvm_arguments("-Xmx4G".to_string());
+        survival.add_tag("modded".to_string());
+        survival.add_tag("modded".to_string());
+        survival.save(&paths).unwrap();
+        Instance::new("creative", "1.17.1", "").save(&paths).unwrap();
+        std::fs::create_dir_all(paths.join(Vec::from(["instances", "not-an-instance"]))).unwrap();
+
+        let loaded = Instance::load(&paths, "survival").unwrap();
+        assert_eq!(loaded.version(), INSTANCE_FORMAT_VERSION);
+        assert_eq!(loaded.game_version(), "1.16.5");
+        assert_eq!(loaded.flavor(), &InstanceFlavor::FABRIC);
+        assert_eq!(loaded.r#type(), &InstanceType::CLIENT);
+        assert_eq!(loaded.selected_account(), "duck@example.com");
+        assert_eq!(loaded.jvm_arguments().map(String::as_str), Some("-Xmx4G"));
+        assert_eq!(loaded.tags(), Some(&Vec::from(["modded".to_string()])));
+
+        let names: Vec<String> = Instance::list(&paths).unwrap().iter().map(|instance| instance.name().to_string()).collect();
+        assert_eq!(names, Vec::from(["creative".to_string(), "survival".to_string()]));
+
+        Instance::delete(&paths, "survival").unwrap();
+        assert!(!survival.directory(&paths).exists());
+        assert!(matches!(Instance::load(&paths, "survival"), Err(LauncherError::InstanceNotFound(_))));
+        assert!(matches!(Instance::delete(&paths, "survival"), Err(LauncherError::InstanceNotFound(_))));
+    }
+
+    #[test]
+    fn names_that_leave_the_instances_directory_are_rejected() {
+        let paths = temp_paths("instance-names");
+        for name in Vec::from(["", ".", "..", "../escape", "nested/name", "nested\\name"]) {
+            assert!(matches!(Instance::validate_name(name), Err(LauncherError::InvalidInstance(_))), "{:?} was accepted", name);
+            assert!(matches!(Instance::new(name, "1.16.5", "").save(&paths), Err(LauncherError::InvalidInstance(_))), "{:?} was saved", name);
+            assert!(matches!(Instance::load(&paths, name), Err(LauncherError::InvalidInstance(_))), "{:?} was loaded", name);
+        }
+        assert!(Instance::validate_name("1.16.5 survival").is_ok());
+        assert!(!paths.root().join("escape").exists());
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let paths = temp_paths("instance-migrate");
+        write_instance(&paths, "1.16.5", r#"{ "selectedAccount": "duck@example.com", "jvmArguments": "-Xmx2G" }"#);
+
+        let instance = Instance::load(&paths, "1.16.5").unwrap();
+        assert_eq!(instance.version(), INSTANCE_FORMAT_VERSION);
+        assert_eq!(instance.name(), "1.16.5");
+        assert_eq!(instance.game_version(), "1.16.5");
+        assert_eq!(instance.r#type(), &InstanceType::CLIENT);
+        assert_eq!(instance.flavor(), &InstanceFlavor::VANILLA);
+        assert_eq!(instance.selected_account(), "duck@example.com");
+        assert_eq!(instance.jvm_arguments().map(String::as_str), Some("-Xmx2G"));
+    }
+
+    #[test]
+    fn unknown_format_versions_are_refused() {
+        let paths = temp_paths("instance-versions");
+        write_instance(&paths, "future", r#"{ "version": 2, "name": "future", "gameVersion": "1.16.5", "type": "client", "flavor": "vanilla", "selectedAccount": "" }"#);
+        write_instance(&paths, "garbled", r#"{ "version": "one", "name": "garbled" }"#);
+
+        assert!(matches!(Instance::load(&paths, "future"), Err(LauncherError::InvalidInstance(_))));
+        assert!(matches!(Instance::load(&paths, "garbled"), Err(LauncherError::InvalidInstance(_))));
+        assert!(Instance::list(&paths).unwrap().is_empty());
+    }
+}
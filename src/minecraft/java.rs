@@ -0,0 +1,305 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::rule;
+
+#[cfg(target_os = "windows")]
+const JAVA_EXECUTABLE: &str = "java.exe";
+#[cfg(not(target_os = "windows"))]
+const JAVA_EXECUTABLE: &str = "java";
+
+// Directories whose children are Java homes.
+#[cfg(target_os = "windows")]
+const JAVA_DIRECTORIES: [&str; 5] = ["C:\\Program Files\\Java", "C:\\Program Files\\Eclipse Adoptium", "C:\\Program Files\\Microsoft", "C:\\Program Files\\Zulu", "C:\\Program Files (x86)\\Java"];
+#[cfg(target_os = "macos")]
+const JAVA_DIRECTORIES: [&str; 2] = ["/Library/Java/JavaVirtualMachines", "/System/Library/Java/JavaVirtualMachines"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const JAVA_DIRECTORIES: [&str; 4] = ["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java", "/opt/java"];
+
+// A Java installation whose version and architecture have been probed.
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRuntime {
+    pub path: PathBuf,
+    pub version: String,
+    pub major_version: u32,
+    pub arch: String,
+}
+
+// Runtimes registered by the user, stored in java.json at the root of the data directory.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JavaRuntimes {
+    pub version: u8,
+    pub runtimes: Vec<PathBuf>,
+}
+
+impl JavaRuntime {
+    /// Runs `java -XshowSettings:properties -version` and reads java.version and os.arch from its output.
+    pub fn probe(path: &Path) -> Result<JavaRuntime, LauncherError> {
+        let output = match std::process::Command::new(path).args(["-XshowSettings:properties", "-version"]).output() {
+            Ok(output) => output,
+            Err(e) => return Err(LauncherError::Launch(format!("Unable to run {}: {}", path.display(), e))),
+        };
+        // The settings go to stderr, but some builds print them to stdout.
+        let properties = String::from_utf8_lossy(&output.stderr).to_string() + &String::from_utf8_lossy(&output.stdout);
+        let property = |name: &str| {
+            properties.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                if key.trim().eq(name) { Some(value.trim().to_string()) } else { None }
+            })
+        };
+        let version = match property("java.version") {
+            Some(version) => version,
+            None => return Err(LauncherError::Launch(format!("{} did not report a Java version", path.display()))),
+        };
+        Ok(JavaRuntime {
+            path: path.to_path_buf(),
+            major_version: JavaRuntime::parse_major_version(&version),
+            version,
+            arch: property("os.arch").unwrap_or_default(),
+        })
+    }
+
+    // 1.8.0_292 is Java 8, 17.0.1 and 17 are Java 17.
+    pub fn parse_major_version(version: &str) -> u32 {
+        let mut parts = version.split(|c: char| !c.is_ascii_digit());
+        match parts.next().and_then(|major| major.parse().ok()) {
+            Some(1) => parts.next().and_then(|minor| minor.parse().ok()).unwrap_or(1),
+            Some(major) => major,
+            None => 0,
+        }
+    }
+}
+
+impl JavaRuntimes {
+    pub fn load(paths: &LauncherPaths) -> Result<JavaRuntimes, LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["java.json"]));
+        if !path.exists() {
+            return Ok(JavaRuntimes {
+                version: 1,
+                runtimes: vec![],
+            });
+        }
+        let mut file = File::open(path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        let runtimes: JavaRuntimes = serde_json::from_str(&data)?;
+        Ok(runtimes)
+    }
+
+    pub fn save(&self, paths: &LauncherPaths) -> Result<(), LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["java.json"]));
+        std::fs::create_dir_all(paths.root())?;
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &self)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, path: PathBuf) {
+        if !self.runtimes.contains(&path) {
+            self.runtimes.push(path);
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<PathBuf> {
+        let index = self.runtimes.iter().position(|runtime| runtime.eq(path))?;
+        Some(self.runtimes.remove(index))
+    }
+}
+
+// The executable of a Java home, or the path itself if it already is one.
+pub fn java_executable(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join("bin").join(JAVA_EXECUTABLE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Every Java runtime that can be found: the registered ones, JAVA_HOME, PATH, the runtimes under
+/// runtime/ and the platform's usual install directories. Candidates that fail to run are skipped.
+pub fn discover(paths: &LauncherPaths) -> Result<Vec<JavaRuntime>, LauncherError> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for runtime in JavaRuntimes::load(paths)?.runtimes {
+        candidates.push(java_executable(&runtime));
+    }
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        candidates.push(java_executable(Path::new(&java_home)));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        for directory in std::env::split_paths(&path) {
+            candidates.push(directory.join(JAVA_EXECUTABLE));
+        }
+    }
+    let mut directories: Vec<PathBuf> = Vec::from([paths.join(Vec::from(["runtime"]))]);
+    directories.extend(JAVA_DIRECTORIES.iter().map(PathBuf::from));
+    for directory in directories {
+        candidates.extend(find_java_homes(&directory).iter().map(|home| java_executable(home)));
+    }
+
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut runtimes: Vec<JavaRuntime> = Vec::new();
+    for candidate in candidates {
+        let canonical = match candidate.canonicalize() {
+            Ok(canonical) if canonical.is_file() => canonical,
+            _ => continue,
+        };
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        match JavaRuntime::probe(&candidate) {
+            Ok(runtime) => runtimes.push(runtime),
+            Err(e) => log::debug!("Skipping {}: {}", candidate.display(), e),
+        }
+    }
+    Ok(runtimes)
+}
+
+// Java homes up to four levels below a directory, e.g. /usr/lib/jvm/java-17-openjdk or
+// runtime/java-runtime-gamma/jre.bundle/Contents/Home.
+fn find_java_homes(directory: &Path) -> Vec<PathBuf> {
+    let mut homes: Vec<PathBuf> = Vec::new();
+    let mut pending: Vec<(PathBuf, u8)> = Vec::from([(directory.to_path_buf(), 0)]);
+    while let Some((directory, depth)) = pending.pop() {
+        if directory.join("bin").join(JAVA_EXECUTABLE).is_file() {
+            homes.push(directory);
+            continue;
+        }
+        if depth >= 4 {
+            continue;
+        }
+        if let Ok(entries) = std::fs::read_dir(&directory) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    pending.push((entry.path(), depth + 1));
+                }
+            }
+        }
+    }
+    homes.sort();
+    homes
+}
+
+/// Picks the runtime for a Java major version on the running architecture. The exact major version
+/// wins; Java 16 and newer also accept a newer runtime, while Java 8 versions break on newer ones.
+pub fn select(runtimes: &[JavaRuntime], major_version: u32) -> Option<&JavaRuntime> {
+    let arch = rule::current_os_arch();
+    let compatible: Vec<&JavaRuntime> = runtimes.iter().filter(|runtime| runtime.arch.eq(arch)).collect();
+    if let Some(runtime) = compatible.iter().find(|runtime| runtime.major_version == major_version) {
+        return Some(runtime);
+    }
+    if major_version < 16 {
+        return None;
+    }
+    compatible.into_iter().filter(|runtime| runtime.major_version > major_version).min_by_key(|runtime| runtime.major_version)
+}
+
+/// The Java executable to run a version with: `java` if given, otherwise the best discovered runtime.
+pub fn resolve(paths: &LauncherPaths, java: Option<&Path>, major_version: u32) -> Result<PathBuf, LauncherError> {
+    if let Some(java) = java {
+        let executable = java_executable(java);
+        if !executable.is_file() {
+            return Err(LauncherError::Launch(format!("Java runtime {} does not exist", executable.display())));
+        }
+        return Ok(executable);
+    }
+    let runtimes = discover(paths)?;
+    match select(&runtimes, major_version) {
+        Some(runtime) => {
+            log::info!("Using Java {} from {}", runtime.version, runtime.path.display());
+            Ok(runtime.path.to_owned())
+        }
+        None => Err(LauncherError::Launch(format!("No Java {} runtime found, register one or set the Java path of the instance", major_version))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::temp_paths;
+
+    fn runtime(name: &str, major_version: u32, arch: &str) -> JavaRuntime {
+        JavaRuntime {
+            path: PathBuf::from(name),
+            version: major_version.to_string(),
+            major_version,
+            arch: arch.to_string(),
+        }
+    }
+
+    fn selected(runtimes: &[JavaRuntime], major_version: u32) -> Option<&str> {
+        select(runtimes, major_version).map(|runtime| runtime.path.to_str().unwrap())
+    }
+
+    // A Java home whose java reports `version` and `arch` like -XshowSettings:properties does.
+    #[cfg(unix)]
+    fn stub_java(home: &Path, version: &str, arch: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let java = home.join("bin").join(JAVA_EXECUTABLE);
+        std::fs::create_dir_all(java.parent().unwrap()).unwrap();
+        std::fs::write(&java, format!("#!/bin/sh\necho 'Property settings:' >&2\necho '    java.version = {}' >&2\necho '    os.arch = {}' >&2\n", version, arch)).unwrap();
+        std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+        java
+    }
+
+    #[test]
+    fn major_versions_follow_both_numbering_schemes() {
+        assert_eq!(JavaRuntime::parse_major_version("1.8.0_292"), 8);
+        assert_eq!(JavaRuntime::parse_major_version("1.7.0_80"), 7);
+        assert_eq!(JavaRuntime::parse_major_version("17.0.2"), 17);
+        assert_eq!(JavaRuntime::parse_major_version("21"), 21);
+        assert_eq!(JavaRuntime::parse_major_version("22-ea"), 22);
+        assert_eq!(JavaRuntime::parse_major_version("unknown"), 0);
+    }
+
+    #[test]
+    fn the_exact_major_version_on_the_running_arch_wins() {
+        let arch = rule::current_os_arch();
+        let runtimes = Vec::from([runtime("foreign-17", 17, "ppc64le"), runtime("native-21", 21, arch), runtime("native-17", 17, arch)]);
+        assert_eq!(selected(&runtimes, 17), Some("native-17"));
+        assert_eq!(selected(&runtimes, 21), Some("native-21"));
+        assert_eq!(selected(&[runtime("foreign-17", 17, "ppc64le")], 17), None);
+    }
+
+    #[test]
+    fn only_java_16_and_newer_fall_back_to_a_newer_runtime() {
+        let arch = rule::current_os_arch();
+        let runtimes = Vec::from([runtime("native-25", 25, arch), runtime("native-21", 21, arch), runtime("foreign-18", 18, "ppc64le")]);
+        assert_eq!(selected(&runtimes, 17), Some("native-21"));
+        assert_eq!(selected(&runtimes, 8), None);
+        assert_eq!(selected(&runtimes, 26), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn registered_runtimes_come_before_java_home_and_are_probed_once() {
+        let paths = temp_paths("java");
+        let registered = paths.root().join("registered");
+        let java_home = paths.root().join("java-home");
+        let installed = paths.join(Vec::from(["runtime", "java-runtime-gamma", "linux"]));
+        stub_java(&registered, "17.0.2", "amd64");
+        stub_java(&java_home, "1.8.0_292", "amd64");
+        let installed_java = stub_java(&installed, "21.0.1", "aarch64");
+        JavaRuntimes { version: 1, runtimes: Vec::from([registered.to_owned(), installed_java.to_owned()]) }.save(&paths).unwrap();
+
+        std::env::set_var("JAVA_HOME", &java_home);
+        let runtimes = discover(&paths).unwrap();
+        std::env::remove_var("JAVA_HOME");
+        let found: Vec<(PathBuf, u32, &str)> = runtimes
+            .iter()
+            .filter(|runtime| runtime.path.starts_with(paths.root()))
+            .map(|runtime| (runtime.path.to_owned(), runtime.major_version, runtime.arch.as_str()))
+            .collect();
+        assert_eq!(found, Vec::from([
+            (registered.join("bin").join(JAVA_EXECUTABLE), 17, "amd64"),
+            (installed_java, 21, "aarch64"),
+            (java_home.join("bin").join(JAVA_EXECUTABLE), 8, "amd64"),
+        ]));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+}
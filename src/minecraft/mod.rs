@@ -1,235 +1,562 @@
-use std::fs::File;
-use std::io;
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use zip::ZipArchive;
-
-use crate::common;
-use crate::minecraft::version::Version;
-use crate::minecraft::yggdrasil::AuthenticateResponse;
-use std::process::Stdio;
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::dependency::LibrariesMetadata;
+use crate::minecraft::version::{Version, VersionDownloadObject};
+use crate::minecraft::account::Account;
+use crate::minecraft::rule::RuleContext;
+use crate::minecraft::process::GameProcess;
+use crate::minecraft::template::{Placeholder, TemplateVariables};
 
+pub mod account;
 pub mod asset;
 pub mod dependency;
+pub mod fabric;
+pub mod forge;
+pub mod instance;
+pub mod java;
+pub mod natives;
+pub mod neoforge;
+pub mod process;
+pub mod rule;
+pub mod runtime;
+pub mod template;
 pub mod version;
 pub mod version_manifest;
 pub mod yggdrasil;
 
-pub enum InstanceType{
-    CLIENT,
-    SERVER,
-}
+impl Version{
+    /// Records the version in the libraries metadata, verifies assets, libraries, natives and the
+    /// client jar, and downloads everything that is missing or corrupt.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        LibrariesMetadata::new(paths)?.push_mc_version(self, scheduler).await?.save(paths)?;
+        self.verify_assets(paths, scheduler).await?;
+        self.verify_libraries(paths, scheduler)?;
+        self.verify_natives(paths, scheduler)?;
+        self.verify_client(paths, scheduler)?;
+        let report = scheduler.run().await;
+        if !report.is_success() {
+            return Err(LauncherError::Download(report));
+        }
+        Ok(())
+    }
 
-pub enum InstanceFlavor{
-    FABRIC,
-    FORGE,
-    VANILLA
-}
+    /// Records the version in the libraries metadata and downloads the dedicated server jar if it is
+    /// missing or corrupt.
+    pub async fn install_server(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        LibrariesMetadata::new(paths)?.push_mc_version(self, scheduler).await?.save(paths)?;
+        self.verify_server(paths, scheduler)?;
+        let report = scheduler.run().await;
+        if !report.is_success() {
+            return Err(LauncherError::Download(report));
+        }
+        Ok(())
+    }
 
-pub struct Instance{
-    version: u8,
-    tags: Option<Vec<String>>,
-    name: String,
-    r#type: InstanceType,
-    flavor: InstanceFlavor,
-    selected_account: String,
-    jvm_arguments: Option<String>,
+    pub fn verify_client(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        verify_jar(scheduler, "client", self.client_path(paths), self.downloads.client.as_ref())
+    }
+
+    /// Libraries, natives and the game jar, copying the client jar to the game jar path first when
+    /// the two differ and the copy is missing or outdated.
+    pub fn get_classpath(&self, paths: &LauncherPaths) -> Result<Vec<PathBuf>, LauncherError> {
+        let game_jar: PathBuf = self.game_jar_path(paths);
+        let client: PathBuf = self.client_path(paths);
+        if game_jar != client {
+            let outdated = match (game_jar.metadata(), client.metadata()) {
+                (Ok(copy), Ok(original)) => copy.len() != original.len(),
+                _ => true,
+            };
+            if outdated {
+                if let Some(parent) = game_jar.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(&client, &game_jar)?;
+            }
+        }
+        let mut classpath: Vec<PathBuf> = self.get_required_libraries_paths(paths);
+        classpath.extend(self.get_required_natives_paths(paths));
+        classpath.push(game_jar);
+        Ok(classpath)
+    }
+
+    pub fn verify_server(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler) -> Result<(), LauncherError> {
+        verify_jar(scheduler, "server", self.server_path(paths), self.downloads.server.as_ref())
+    }
 }
 
-pub struct Account{
-    access_token: String,
-    active_profile: String,
-    profiles: Vec<Profile>,
-    user: User,
-    username: String
+// A jar with a download is queued when it is missing or corrupt. One without a download cannot be
+// repaired, so it is an error when it is missing.
+fn verify_jar(scheduler: &DownloadScheduler, name: &str, path: PathBuf, download: Option<&VersionDownloadObject>) -> Result<(), LauncherError> {
+    match download {
+        Some(download) => {
+            let task = DownloadTask::new(&download.url, path).with_sha1(&download.sha1).with_size(download.size);
+            scheduler.submit_invalid(name, Vec::from([task]));
+            Ok(())
+        }
+        None if path.is_file() => Ok(()),
+        None => Err(LauncherError::MissingFile(path)),
+    }
 }
 
-pub struct Profile{
-    id: String,
-    legacy: bool,
-    name: String,
+pub struct LaunchOptions {
+    pub game_directory: PathBuf,
+    pub natives_directory: PathBuf,
+    pub java: PathBuf,
+    pub jvm_arguments: Vec<String>,
+    pub rule_context: RuleContext,
+    pub resolution: Option<(u32, u32)>,
+    pub quick_play_path: Option<PathBuf>,
+    pub quick_play: Option<QuickPlay>,
 }
 
-pub struct User{
-    id: String
+// Where 1.20+ clients go straight to after starting, instead of the title screen.
+#[derive(Debug, Clone)]
+pub enum QuickPlay {
+    Singleplayer(String),
+    Multiplayer(String),
+    Realms(String),
 }
 
-impl Version{
-    pub async fn verify_client(&self) -> Result<(), reqwest::Error> {
-        let path: PathBuf = common::join_directories(Vec::from(["libraries", "com", "mojang", "minecraft", &self.id, "client", &format!("{}.jar", self.id)])).unwrap();
-        if path.exists() {
-            if path.metadata().unwrap().len() != self.downloads.client.as_ref().unwrap().size {
-                match common::file_downloader::from_url(&self.downloads.client.as_ref().unwrap().url, &path).await {
-                    Ok(()) => {}
-                    Err(e) => panic!("{}", e)
-                }
-            }
-        } else {
-            match common::file_downloader::from_url(&self.downloads.client.as_ref().unwrap().url, &path).await {
-                Ok(()) => {}
-                Err(e) => panic!("{}", e)
-            }
+impl LaunchOptions {
+    pub fn new(game_directory: PathBuf, natives_directory: PathBuf) -> LaunchOptions {
+        LaunchOptions {
+            game_directory,
+            natives_directory,
+            java: PathBuf::from("java"),
+            jvm_arguments: Vec::new(),
+            rule_context: RuleContext::current(),
+            resolution: None,
+            quick_play_path: None,
+            quick_play: None,
         }
-        Ok(())
+    }
+
+    pub fn with_java(mut self, java: PathBuf) -> LaunchOptions {
+        self.java = java;
+        self
+    }
+
+    pub fn with_jvm_arguments(mut self, jvm_arguments: Vec<String>) -> LaunchOptions {
+        self.jvm_arguments = jvm_arguments;
+        self
+    }
+
+    pub fn with_resolution(mut self, width: u32, height: u32) -> LaunchOptions {
+        self.resolution = Some((width, height));
+        self.with_feature(rule::FEATURE_CUSTOM_RESOLUTION, true)
+    }
+
+    // The file the game logs quick play sessions to.
+    pub fn with_quick_play_path(mut self, quick_play_path: PathBuf) -> LaunchOptions {
+        self.quick_play_path = Some(quick_play_path);
+        self.with_feature(rule::FEATURE_QUICK_PLAYS_SUPPORT, true)
+    }
+
+    /// Joins a world (by folder name), a server (host:port) or a realm (by id) right after start.
+    pub fn with_quick_play(mut self, quick_play: QuickPlay) -> LaunchOptions {
+        let feature = match &quick_play {
+            QuickPlay::Singleplayer(_) => rule::FEATURE_QUICK_PLAY_SINGLEPLAYER,
+            QuickPlay::Multiplayer(_) => rule::FEATURE_QUICK_PLAY_MULTIPLAYER,
+            QuickPlay::Realms(_) => rule::FEATURE_QUICK_PLAY_REALMS,
+        };
+        self.quick_play = Some(quick_play);
+        self.with_feature(feature, true)
+    }
+
+    // Features such as has_custom_resolution that argument rules are evaluated against.
+    pub fn with_feature(mut self, feature: &str, enabled: bool) -> LaunchOptions {
+        self.rule_context = self.rule_context.with_feature(feature, enabled);
+        self
     }
 }
 
-impl Instance{
-    pub fn new(name: &str, selected_account: &str) -> Instance{
-        Instance{
-            version: 1,
-            tags: None,
-            name: name.to_string(),
-            r#type: InstanceType::CLIENT,
-            flavor: InstanceFlavor:: VANILLA,
-            selected_account: selected_account.to_string(),
-            jvm_arguments: None
-        }
+/// Starts the game without waiting for it; the returned handle follows its output and lifetime.
+/// Has to be called from within a tokio runtime.
+pub fn launch_client(paths: &LauncherPaths, account: &Account, version: &Version, options: &LaunchOptions) -> Result<GameProcess, LauncherError> {
+    let profile = match account.get_active_profile() {
+        Some(profile) => profile,
+        None => return Err(LauncherError::Auth(String::from("Account has no selected profile"))),
+    };
+    let natives_directory: PathBuf = version.extract_natives(paths, &options.natives_directory)?;
+    let classpath_separator = if std::env::consts::OS.eq("windows") { ";" } else { ":" };
+    let classpath: Vec<String> = version.get_classpath(paths)?.iter().map(|path| path.to_string_lossy().to_string()).collect();
+
+    let mut variables = TemplateVariables::new()
+        .with(Placeholder::AuthPlayerName, &profile.name)
+        .with(Placeholder::AuthUuid, &profile.id)
+        .with(Placeholder::AuthAccessToken, &account.access_token)
+        .with(Placeholder::AuthSession, &format!("token:{}:{}", account.access_token, profile.id))
+        .with(Placeholder::AuthXuid, "")
+        .with(Placeholder::ClientId, &account.client_token)
+        .with(Placeholder::UserType, "mojang")
+        .with(Placeholder::UserProperties, "{}")
+        .with(Placeholder::VersionName, &version.id)
+        .with(Placeholder::VersionType, &version.r#type)
+        .with(Placeholder::GameDirectory, &options.game_directory.to_string_lossy())
+        .with(Placeholder::GameAssets, &version.get_game_assets_path(paths).to_string_lossy())
+        .with(Placeholder::AssetsRoot, &paths.join(Vec::from(["assets"])).to_string_lossy())
+        .with(Placeholder::AssetsIndexName, &version.asset_index.id)
+        .with(Placeholder::NativesDirectory, &natives_directory.to_string_lossy())
+        .with(Placeholder::LibraryDirectory, &paths.join(Vec::from(["libraries"])).to_string_lossy())
+        .with(Placeholder::Classpath, &classpath.join(classpath_separator))
+        .with(Placeholder::ClasspathSeparator, classpath_separator)
+        .with(Placeholder::LauncherName, "DuckLauncher")
+        .with(Placeholder::LauncherVersion, "1");
+    if let Some((width, height)) = options.resolution {
+        variables.set(Placeholder::ResolutionWidth, &width.to_string());
+        variables.set(Placeholder::ResolutionHeight, &height.to_string());
     }
+    if let Some(quick_play_path) = &options.quick_play_path {
+        variables.set(Placeholder::QuickPlayPath, &quick_play_path.to_string_lossy());
+    }
+    match &options.quick_play {
+        Some(QuickPlay::Singleplayer(world)) => variables.set(Placeholder::QuickPlaySingleplayer, world),
+        Some(QuickPlay::Multiplayer(server)) => variables.set(Placeholder::QuickPlayMultiplayer, server),
+        Some(QuickPlay::Realms(realm)) => variables.set(Placeholder::QuickPlayRealms, realm),
+        None => {}
+    }
+    let jvm_arguments = variables.render_all(&version.get_jvm_arguments(&options.rule_context))?;
+    let game_arguments = variables.render_all(&version.get_game_arguments(&options.rule_context))?;
 
-    pub fn add_tag(mut self, tag: String){
-        match self.tags {
-            Some(mut tags) => {
-                tags.push(tag);
-            },
-            None => {
-                let tags: Vec<String> = Vec::from([tag]);
-                self.tags = Some(tags);
-            }
+    std::fs::create_dir_all(&options.game_directory)?;
+    let mut command = tokio::process::Command::new(&options.java);
+    for jvm_argument in jvm_arguments.iter().chain(options.jvm_arguments.iter()) {
+        command.arg(jvm_argument);
+    }
+    command.current_dir(&options.game_directory);
+    command.arg(&version.main_class);
+    command.args(&game_arguments);
+    let process = GameProcess::spawn(command)?;
+    log::info!("Started {} with pid {}", version.id, process.pid().map(|pid| pid.to_string()).unwrap_or_default());
+    Ok(process)
+}
+
+pub const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+
+pub struct ServerOptions {
+    pub game_directory: PathBuf,
+    pub java: PathBuf,
+    pub jvm_arguments: Vec<String>,
+    pub properties: BTreeMap<String, String>,
+    pub accept_eula: bool,
+    pub arguments_file: Option<PathBuf>,
+}
+
+impl ServerOptions {
+    pub fn new(game_directory: PathBuf) -> ServerOptions {
+        ServerOptions {
+            game_directory,
+            java: PathBuf::from("java"),
+            jvm_arguments: Vec::new(),
+            properties: BTreeMap::new(),
+            accept_eula: false,
+            arguments_file: None,
         }
     }
 
-    pub fn with_type(mut self, r#type: InstanceType) -> Instance{
-        self.r#type = r#type;
+    pub fn with_java(mut self, java: PathBuf) -> ServerOptions {
+        self.java = java;
         self
     }
 
-    pub fn with_flavor(mut self, flavor: InstanceFlavor) -> Instance{
-        self.flavor = flavor;
+    pub fn with_jvm_arguments(mut self, jvm_arguments: Vec<String>) -> ServerOptions {
+        self.jvm_arguments = jvm_arguments;
         self
     }
 
-    pub fn with_account(mut self, selected_account: String) -> Instance{
-        self.selected_account = selected_account;
+    pub fn with_properties(mut self, properties: BTreeMap<String, String>) -> ServerOptions {
+        self.properties = properties;
         self
     }
 
-    pub fn with_jvm_arguments(mut self, jvm_arguments: String) -> Instance{
-        self.jvm_arguments = Some(jvm_arguments);
+    // The EULA is only written once the user has agreed to it, a server refuses to start without it.
+    pub fn with_accept_eula(mut self, accept_eula: bool) -> ServerOptions {
+        self.accept_eula = accept_eula;
+        self
+    }
+
+    // Forge and NeoForge 1.17+ servers start from the unix_args.txt or win_args.txt their installer
+    // leaves in libraries/ instead of a runnable jar.
+    pub fn with_arguments_file(mut self, arguments_file: PathBuf) -> ServerOptions {
+        self.arguments_file = Some(arguments_file);
         self
     }
 }
 
+pub fn is_eula_accepted(game_directory: &Path) -> bool {
+    match std::fs::read_to_string(game_directory.join("eula.txt")) {
+        Ok(eula) => eula.lines().any(|line| line.trim().eq_ignore_ascii_case("eula=true")),
+        Err(_) => false,
+    }
+}
 
-pub fn launch_client(authentication_response: &AuthenticateResponse, version: &Version) {
-    let mut jvm_arguments: Vec<String> = Vec::new();
-    let mut game_arguments: Vec<String> = Vec::new();
-    let auth = authentication_response;
-    match &version.arguments {
-        Some(val) => {
-            for jvm_argument in &val.jvm {
-                if jvm_argument.is_string() {
-                    let new_arg: String = {
-                        let arg: &str = jvm_argument.as_str().unwrap();
-                        if arg.contains("${launcher_name}") {
-                            arg.replace("${launcher_name}", "DuckLauncher")
-                        } else if arg.contains("${natives_directory}") {
-                            //todo: extract natives
-                            let natives_path = common::join_directories(Vec::from(["instances", &version.id, "natives"])).unwrap();
-                            for path in version.get_required_natives_paths() {
-                                let file = File::open(path).expect("");
-                                let mut zip = ZipArchive::new(file).expect("");
-                                zip.extract(&natives_path);
-                            }
-                            arg.replace("${natives_directory}", &natives_path.into_os_string().into_string().unwrap())
-                        } else if arg.contains("${launcher_version}") {
-                            arg.replace("${launcher_version}", "1")
-                        } else if arg.contains("${classpath}") {
-                            let mut paths: Vec<PathBuf> = Vec::new();
-                            for get_required_libraries_path in version.get_required_libraries_paths() {
-                                paths.push(get_required_libraries_path);
-                            }
-                            for get_natives_path in version.get_required_natives_paths() {
-                                paths.push(get_natives_path);
-                            }
-                            let path: PathBuf = common::join_directories(Vec::from(["libraries", "com", "mojang", "minecraft", &version.id, "client", &format!("{}.jar", version.id)])).unwrap();
-                            paths.push(path);
-
-                            let mut builder: String = String::new();
-                            for path in paths {
-                                if builder.is_empty() {
-                                    builder.push_str(&path.into_os_string().into_string().unwrap());
-                                } else {
-                                    let os = std::env::consts::OS;
-                                    if os.eq("windows") {
-                                        builder.push_str(&format!(";{}", path.into_os_string().into_string().unwrap()));
-                                    } else if os.eq("linux") || os.eq("macos") {
-                                        builder.push_str(&format!(":{}", path.into_os_string().into_string().unwrap()));
-                                    }
-                                }
-                            }
-
-                            arg.replace("${classpath}", builder.as_str())
-                        } else {
-                            arg.to_string()
-                        }
-                    };
-                    jvm_arguments.push(new_arg);
-                }
+pub fn accept_eula(game_directory: &Path) -> Result<(), LauncherError> {
+    std::fs::create_dir_all(game_directory)?;
+    std::fs::write(game_directory.join("eula.txt"), format!("#By changing the setting below to TRUE you are indicating your agreement to our EULA ({}).\neula=true\n", EULA_URL))?;
+    Ok(())
+}
+
+/// Writes `properties` into server.properties, replacing the values of keys that are already in the file
+/// and keeping everything else the server wrote there.
+pub fn write_server_properties(game_directory: &Path, properties: &BTreeMap<String, String>) -> Result<(), LauncherError> {
+    let path: PathBuf = game_directory.join("server.properties");
+    let existing = if path.is_file() { std::fs::read_to_string(&path)? } else { String::new() };
+    let mut written: Vec<&str> = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    for line in existing.lines() {
+        let key = line.split('=').next().unwrap_or_default().trim();
+        match properties.get_key_value(key) {
+            Some((key, value)) if !line.trim_start().starts_with('#') => {
+                lines.push(format!("{}={}", key, escape_property(value)));
+                written.push(key);
             }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    for (key, value) in properties {
+        if !written.contains(&key.as_str()) {
+            lines.push(format!("{}={}", key, escape_property(value)));
+        }
+    }
+    std::fs::create_dir_all(game_directory)?;
+    std::fs::write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
 
+// Backslashes start escapes in a properties file and a line break would start another property.
+fn escape_property(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
 
-            for game_argument in &val.game {
-                if game_argument.is_string() {
-                    let arg: &str = game_argument.as_str().unwrap();
-                    if arg.contains("${") && arg.contains('}') {
-                        if arg.contains("auth_player_name") {
-                            game_arguments.push(auth.selected_profile.as_ref().unwrap().name.as_ref().unwrap().to_owned());
-                        }
-                        if arg.contains("version_name") {
-                            game_arguments.push(version.id.to_owned());
-                        }
-                        if arg.contains("game_directory") {
-                            let game_directory = common::join_directories(Vec::from(["instances", &version.id, ".minecraft"])).unwrap().into_os_string().into_string().unwrap();
-                            game_arguments.push(game_directory);
-                        }
-                        if arg.contains("assets_root") {
-                            let assets_root = common::join_directories(Vec::from(["assets"])).unwrap().into_os_string().into_string().unwrap();
-                            game_arguments.push(assets_root);
-                        }
-                        if arg.contains("assets_index_name") {
-                            game_arguments.push(version.asset_index.id.to_owned());
-                        }
-                        if arg.contains("auth_uuid") {
-                            game_arguments.push(auth.selected_profile.as_ref().unwrap().id.as_ref().unwrap().to_owned());
-                        }
-                        if arg.contains("auth_access_token") {
-                            game_arguments.push(auth.access_token.as_ref().unwrap().to_owned());
-                        }
-                        if arg.contains("user_type") {
-                            game_arguments.push(String::from("mojang"));
-                        }
-                        if arg.contains("version_type") {
-                            game_arguments.push(version.r#type.to_owned());
-                        }
-                    } else {
-                        game_arguments.push(arg.to_owned());
-                    }
+/// The arguments java runs the server with: the JVM arguments, then either the contents of the
+/// arguments file or the dedicated server jar of `version`.
+pub fn server_arguments(paths: &LauncherPaths, version: &Version, options: &ServerOptions) -> Result<Vec<String>, LauncherError> {
+    let mut arguments: Vec<String> = options.jvm_arguments.to_owned();
+    match &options.arguments_file {
+        Some(arguments_file) => {
+            let separator = if std::env::consts::OS.eq("windows") { ";" } else { ":" };
+            for argument in split_arguments_file(&std::fs::read_to_string(arguments_file)?) {
+                arguments.push(absolute_library_paths(paths, &argument, separator));
+            }
+        }
+        None => {
+            arguments.push(String::from("-jar"));
+            arguments.push(version.server_path(paths).to_string_lossy().to_string());
+        }
+    }
+    arguments.push(String::from("nogui"));
+    Ok(arguments)
+}
+
+// The @argfile syntax of java: arguments are separated by whitespace and may be quoted.
+fn split_arguments_file(data: &str) -> Vec<String> {
+    let mut arguments: Vec<String> = Vec::new();
+    let mut argument = String::new();
+    let mut quote: Option<char> = None;
+    let mut started = false;
+    for c in data.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => argument.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                started = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if started {
+                    arguments.push(std::mem::take(&mut argument));
+                    started = false;
                 }
             }
+            (None, c) => {
+                argument.push(c);
+                started = true;
+            }
         }
-        None => {}
     }
-    let mut command = std::process::Command::new("java");
-    for jvm_argument in jvm_arguments {
-        command.arg(&jvm_argument);
+    if started {
+        arguments.push(argument);
     }
-    command.arg(&version.main_class);
-    for argument in game_arguments {
-        command.arg(&argument);
+    arguments
+}
+
+// The arguments files name libraries relative to the server directory, as `libraries/...`. The server
+// runs in the instance's game directory instead, so they are pointed at the shared libraries directory.
+fn absolute_library_paths(paths: &LauncherPaths, argument: &str, separator: &str) -> String {
+    let (prefix, value) = match argument.split_once('=') {
+        Some((key, value)) if key.starts_with("-D") => (format!("{}=", key), value),
+        _ => (String::new(), argument),
+    };
+    let entries: Vec<String> = value
+        .split(separator)
+        .map(|entry| if entry.eq("libraries") || entry.starts_with("libraries/") { paths.root().join(entry).to_string_lossy().to_string() } else { entry.to_string() })
+        .collect();
+    format!("{}{}", prefix, entries.join(separator))
+}
+
+/// Starts the dedicated server of `version` in the game directory. Commands are sent through the
+/// console of the returned process; send `stop` to shut the server down cleanly.
+pub fn launch_server(paths: &LauncherPaths, version: &Version, options: &ServerOptions) -> Result<GameProcess, LauncherError> {
+    if options.accept_eula {
+        accept_eula(&options.game_directory)?;
+    }
+    if !is_eula_accepted(&options.game_directory) {
+        return Err(LauncherError::Launch(format!("The Minecraft EULA ({}) has to be accepted before running a server", EULA_URL)));
+    }
+    write_server_properties(&options.game_directory, &options.properties)?;
+
+    let mut command = tokio::process::Command::new(&options.java);
+    command.args(server_arguments(paths, version, options)?);
+    command.current_dir(&options.game_directory);
+    GameProcess::spawn_with_console(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_dir, temp_paths, TestServer};
+    use sha1::{Digest, Sha1};
+
+    fn version(downloads: serde_json::Value) -> Version {
+        serde_json::from_value(serde_json::json!({
+            "id": "1.20.1",
+            "arguments": {"game": [], "jvm": []},
+            "assetIndex": {"id": "5", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "assets": "5",
+            "downloads": downloads,
+            "libraries": [],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2023-06-12T13:25:51+00:00",
+            "time": "2023-06-12T13:25:51+00:00",
+            "type": "release"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn server_properties_replace_known_keys_and_keep_the_rest() {
+        let game_directory = temp_dir("server");
+        std::fs::write(game_directory.join("server.properties"), "#Minecraft server properties\n#motd=commented\nmotd=A Minecraft Server\nmax-players=20\n").unwrap();
+        let properties: BTreeMap<String, String> = BTreeMap::from([
+            (String::from("motd"), String::from("Duck\\Server")),
+            (String::from("pvp"), String::from("false")),
+            (String::from("level-name"), String::from("world\nop-permission-level=4")),
+        ]);
+        write_server_properties(&game_directory, &properties).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(game_directory.join("server.properties")).unwrap(),
+            "#Minecraft server properties\n#motd=commented\nmotd=Duck\\\\Server\nmax-players=20\nlevel-name=world\\nop-permission-level=4\npvp=false\n"
+        );
+        std::fs::remove_dir_all(game_directory).unwrap();
+    }
+
+    #[test]
+    fn server_properties_are_created_when_missing() {
+        let game_directory = temp_dir("server").join("game");
+        write_server_properties(&game_directory, &BTreeMap::from([(String::from("server-port"), String::from("25566"))])).unwrap();
+        assert_eq!(std::fs::read_to_string(game_directory.join("server.properties")).unwrap(), "server-port=25566\n");
+        std::fs::remove_dir_all(game_directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn the_eula_is_only_accepted_when_agreed_to() {
+        let game_directory = temp_dir("server");
+        assert!(!is_eula_accepted(&game_directory));
+        std::fs::write(game_directory.join("eula.txt"), "eula=false\n").unwrap();
+        assert!(!is_eula_accepted(&game_directory));
+
+        accept_eula(&game_directory).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(game_directory.join("eula.txt")).unwrap(),
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).\neula=true\n"
+        );
+        assert!(is_eula_accepted(&game_directory));
+
+        let version = version(serde_json::json!({}));
+        let options = ServerOptions::new(temp_dir("server"));
+        let result = launch_server(&temp_paths("server"), &version, &options);
+        assert!(matches!(result, Err(LauncherError::Launch(_))));
+        assert!(!options.game_directory.join("server.properties").exists());
+        std::fs::remove_dir_all(game_directory).unwrap();
+        std::fs::remove_dir_all(options.game_directory).unwrap();
+    }
+
+    #[test]
+    fn vanilla_servers_run_the_server_jar() {
+        let paths = temp_paths("server");
+        let options = ServerOptions::new(paths.root().join("game")).with_jvm_arguments(Vec::from([String::from("-Xmx2G")]));
+        let arguments = server_arguments(&paths, &version(serde_json::json!({})), &options).unwrap();
+        let server_jar = paths.library("com/mojang/minecraft/1.20.1/server/1.20.1.jar").to_string_lossy().to_string();
+        assert_eq!(arguments, Vec::from([String::from("-Xmx2G"), String::from("-jar"), server_jar, String::from("nogui")]));
+        std::fs::remove_dir_all(paths.root()).unwrap();
     }
-    let mut output = command.stdout(Stdio::inherit()).stderr(Stdio::inherit()).spawn().expect("kekw");
-    let status = output.wait();
-    println!("Exited with status {:?}", status);
-}
\ No newline at end of file
+
+    #[test]
+    fn arguments_files_point_at_the_shared_libraries() {
+        let paths = temp_paths("server");
+        let separator = if std::env::consts::OS.eq("windows") { ";" } else { ":" };
+        let arguments_file = paths.library("net/minecraftforge/forge/1.20.1-47.2.0/unix_args.txt");
+        std::fs::create_dir_all(arguments_file.parent().unwrap()).unwrap();
+        let data = format!(
+            "-Djava.net.preferIPv6Addresses=system\n-DlibraryDirectory=libraries\n-p libraries/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar{}libraries/cpw/mods/securejarhandler/2.1.10/securejarhandler-2.1.10.jar\n--add-modules ALL-MODULE-PATH\n\"-Dmotd=A Server\"\ncpw.mods.bootstraplauncher.BootstrapLauncher\n--launchTarget forgeserver\n",
+            separator
+        );
+        std::fs::write(&arguments_file, data).unwrap();
+        let options = ServerOptions::new(paths.root().join("game")).with_arguments_file(arguments_file);
+        let arguments = server_arguments(&paths, &version(serde_json::json!({})), &options).unwrap();
+        let library = |path: &str| paths.root().join(path).to_string_lossy().to_string();
+        let expected: Vec<String> = Vec::from([
+            String::from("-Djava.net.preferIPv6Addresses=system"),
+            format!("-DlibraryDirectory={}", library("libraries")),
+            String::from("-p"),
+            format!("{}{}{}", library("libraries/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar"), separator, library("libraries/cpw/mods/securejarhandler/2.1.10/securejarhandler-2.1.10.jar")),
+            String::from("--add-modules"),
+            String::from("ALL-MODULE-PATH"),
+            String::from("-Dmotd=A Server"),
+            String::from("cpw.mods.bootstraplauncher.BootstrapLauncher"),
+            String::from("--launchTarget"),
+            String::from("forgeserver"),
+            String::from("nogui"),
+        ]);
+        assert_eq!(arguments, expected);
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[test]
+    fn jars_without_a_download_have_to_exist() {
+        let paths = temp_paths("server");
+        let version = version(serde_json::json!({}));
+        let scheduler = DownloadScheduler::new();
+        assert!(matches!(version.verify_server(&paths, &scheduler), Err(LauncherError::MissingFile(path)) if path == version.server_path(&paths)));
+        assert!(matches!(version.verify_client(&paths, &scheduler), Err(LauncherError::MissingFile(path)) if path == version.client_path(&paths)));
+
+        let server_jar = version.server_path(&paths);
+        std::fs::create_dir_all(server_jar.parent().unwrap()).unwrap();
+        std::fs::write(&server_jar, b"server").unwrap();
+        assert!(version.verify_server(&paths, &scheduler).is_ok());
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn corrupt_jars_are_downloaded_again() {
+        let server = TestServer::start(Vec::from([("/server.jar", b"server".to_vec())]));
+        let paths = temp_paths("server");
+        let sha1 = format!("{:x}", Sha1::digest(b"server"));
+        let version = version(serde_json::json!({"server": {"sha1": sha1, "size": 6, "url": server.url("/server.jar")}}));
+        let server_jar = version.server_path(&paths);
+        std::fs::create_dir_all(server_jar.parent().unwrap()).unwrap();
+        std::fs::write(&server_jar, b"broken").unwrap();
+
+        let scheduler = DownloadScheduler::new();
+        version.verify_server(&paths, &scheduler).unwrap();
+        assert!(scheduler.run().await.is_success());
+        assert_eq!(std::fs::read(&server_jar).unwrap(), b"server");
+        assert_eq!(server.requests().len(), 1);
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+}
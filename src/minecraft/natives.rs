@@ -0,0 +1,193 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use sha1::{Digest, Sha1};
+use zip::ZipArchive;
+
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::version::Version;
+
+// Written last, so a directory without it is a partial extraction.
+const EXTRACTED_MARKER: &str = ".extracted";
+
+impl Version {
+    // Changes whenever a natives jar or its exclude list does.
+    pub fn get_natives_key(&self) -> String {
+        let mut hasher = Sha1::new();
+        if let Some(natives) = self.get_required_natives() {
+            for (library, native) in natives {
+                hasher.update(native.sha1.as_bytes());
+                if let Some(extract) = &library.extract {
+                    for exclude in &extract.exclude {
+                        hasher.update(exclude.as_bytes());
+                    }
+                }
+                hasher.update(b"\n");
+            }
+        }
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+
+    /// Extracts the natives jars of the version into `natives_root/<key>`, skipping entries listed in
+    /// their `extract.exclude`, and returns that directory. Nothing is extracted when it is already
+    /// complete; directories left by other natives sets are removed.
+    pub fn extract_natives(&self, paths: &LauncherPaths, natives_root: &Path) -> Result<PathBuf, LauncherError> {
+        let key = self.get_natives_key();
+        let directory: PathBuf = natives_root.join(&key);
+        if directory.join(EXTRACTED_MARKER).is_file() {
+            log::debug!("Natives of {} are up to date in {}", self.id, directory.display());
+        } else {
+            if directory.exists() {
+                std::fs::remove_dir_all(&directory)?;
+            }
+            std::fs::create_dir_all(&directory)?;
+            if let Some(natives) = self.get_required_natives() {
+                for (library, native) in natives {
+                    let exclude: &[String] = library.extract.as_ref().map(|extract| extract.exclude.as_slice()).unwrap_or_default();
+                    extract_jar(&paths.library(&native.path), &directory, exclude)?;
+                }
+            }
+            File::create(directory.join(EXTRACTED_MARKER))?;
+        }
+        remove_stale_natives(natives_root, &key);
+        Ok(directory)
+    }
+}
+
+fn extract_jar(jar: &Path, directory: &Path, exclude: &[String]) -> Result<(), LauncherError> {
+    let mut archive = ZipArchive::new(File::open(jar)?)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() || exclude.iter().any(|prefix| entry.name().starts_with(prefix.as_str())) {
+            continue;
+        }
+        // Entries escaping the directory (../) are skipped.
+        let path: PathBuf = match entry.enclosed_name() {
+            Some(name) => directory.join(name),
+            None => continue,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&path)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+    Ok(())
+}
+
+// Natives of a running game may be locked on Windows, failures are left for the next launch.
+fn remove_stale_natives(natives_root: &Path, key: &str) {
+    let entries = match std::fs::read_dir(natives_root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().eq(key) {
+            continue;
+        }
+        let path = entry.path();
+        let removed = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        if let Err(e) = removed {
+            log::debug!("Unable to remove stale natives {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::common::test_support::{temp_dir, temp_paths};
+
+    const NATIVES_PATH: &str = "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives.jar";
+
+    fn version(exclude: &[&str]) -> Version {
+        let native = serde_json::json!({ "path": NATIVES_PATH, "sha1": "0123456789abcdef", "size": 0, "url": "" });
+        serde_json::from_value(serde_json::json!({
+            "assetIndex": { "id": "1.16", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": "1.16",
+            "downloads": {},
+            "id": "1.16.5",
+            "libraries": [{
+                "name": "org.lwjgl:lwjgl:3.2.2",
+                "downloads": { "classifiers": { "natives-linux": native, "natives-windows": native, "natives-macos": native, "natives-osx": native } },
+                "natives": { "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" },
+                "extract": { "exclude": exclude }
+            }],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2021-01-14T16:05:32+00:00",
+            "time": "2021-01-14T16:05:32+00:00",
+            "type": "release"
+        })).unwrap()
+    }
+
+    fn write_natives_jar(paths: &LauncherPaths) {
+        let path = paths.library(NATIVES_PATH);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut jar = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in Vec::from([("META-INF/MANIFEST.MF", "Manifest-Version: 1.0"), ("liblwjgl.so", "lwjgl"), ("linux/x64/libglfw.so", "glfw")]) {
+            jar.start_file(name, zip::write::FileOptions::default()).unwrap();
+            jar.write_all(content.as_bytes()).unwrap();
+        }
+        jar.finish().unwrap();
+    }
+
+    #[test]
+    fn excluded_entries_are_not_extracted() {
+        let paths = temp_paths("natives");
+        write_natives_jar(&paths);
+        let natives_root = temp_dir("natives-root");
+        let version = version(&["META-INF/"]);
+
+        let directory = version.extract_natives(&paths, &natives_root).unwrap();
+        assert_eq!(directory, natives_root.join(version.get_natives_key()));
+        assert_eq!(std::fs::read_to_string(directory.join("liblwjgl.so")).unwrap(), "lwjgl");
+        assert_eq!(std::fs::read_to_string(directory.join("linux/x64/libglfw.so")).unwrap(), "glfw");
+        assert!(!directory.join("META-INF").exists());
+        assert!(directory.join(EXTRACTED_MARKER).is_file());
+    }
+
+    #[test]
+    fn the_key_follows_the_exclude_list() {
+        assert_eq!(version(&["META-INF/"]).get_natives_key(), version(&["META-INF/"]).get_natives_key());
+        assert_ne!(version(&["META-INF/"]).get_natives_key(), version(&[]).get_natives_key());
+    }
+
+    #[test]
+    fn complete_directories_are_reused_and_partial_ones_extracted_again() {
+        let paths = temp_paths("natives");
+        write_natives_jar(&paths);
+        let natives_root = temp_dir("natives-root");
+        let version = version(&["META-INF/"]);
+
+        let directory = version.extract_natives(&paths, &natives_root).unwrap();
+        std::fs::write(directory.join("liblwjgl.so"), "left alone").unwrap();
+        assert_eq!(version.extract_natives(&paths, &natives_root).unwrap(), directory);
+        assert_eq!(std::fs::read_to_string(directory.join("liblwjgl.so")).unwrap(), "left alone");
+
+        std::fs::remove_file(directory.join(EXTRACTED_MARKER)).unwrap();
+        std::fs::write(directory.join("leftover.so"), "partial").unwrap();
+        version.extract_natives(&paths, &natives_root).unwrap();
+        assert_eq!(std::fs::read_to_string(directory.join("liblwjgl.so")).unwrap(), "lwjgl");
+        assert!(!directory.join("leftover.so").exists());
+        assert!(directory.join(EXTRACTED_MARKER).is_file());
+    }
+
+    #[test]
+    fn natives_of_other_sets_are_removed() {
+        let paths = temp_paths("natives");
+        write_natives_jar(&paths);
+        let natives_root = temp_dir("natives-root");
+        std::fs::create_dir_all(natives_root.join("0123456789abcdef").join("lib")).unwrap();
+        std::fs::write(natives_root.join("0123456789abcdef").join("lib").join("liblwjgl.so"), "old").unwrap();
+        std::fs::write(natives_root.join("liblwjgl.so"), "unkeyed").unwrap();
+
+        let directory = version(&["META-INF/"]).extract_natives(&paths, &natives_root).unwrap();
+        let mut remaining: Vec<PathBuf> = std::fs::read_dir(&natives_root).unwrap().map(|entry| entry.unwrap().path()).collect();
+        remaining.sort();
+        assert_eq!(remaining, Vec::from([directory]));
+    }
+}
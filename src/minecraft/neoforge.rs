@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use crate::common::download_scheduler::DownloadScheduler;
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::forge;
+use crate::minecraft::instance::InstanceType;
+
+pub const NEOFORGE_MAVEN: &str = "https://maven.neoforged.net/releases";
+pub const NEOFORGE_MAVEN_ENV: &str = "DUCK_LAUNCHER_NEOFORGE_MAVEN";
+
+// NeoForge ships Forge-style installers, so installing one goes through forge::run_installer.
+#[derive(Debug, Clone)]
+pub struct NeoForgeMeta {
+    maven_url: String,
+}
+
+impl NeoForgeMeta {
+    pub fn new() -> NeoForgeMeta {
+        NeoForgeMeta {
+            maven_url: NEOFORGE_MAVEN.to_string(),
+        }
+    }
+
+    // The repository named by DUCK_LAUNCHER_NEOFORGE_MAVEN, or the official one.
+    pub fn from_env() -> NeoForgeMeta {
+        match std::env::var(NEOFORGE_MAVEN_ENV) {
+            Ok(maven_url) if !maven_url.is_empty() => NeoForgeMeta::new().with_maven_url(&maven_url),
+            _ => NeoForgeMeta::new(),
+        }
+    }
+
+    pub fn with_maven_url(mut self, maven_url: &str) -> NeoForgeMeta {
+        self.maven_url = maven_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn maven_url(&self) -> &str {
+        &self.maven_url
+    }
+
+    /// Every published NeoForge version, oldest first, as listed by the repository's maven-metadata.xml.
+    pub async fn get_versions(&self, scheduler: &DownloadScheduler) -> Result<Vec<String>, LauncherError> {
+        let url = format!("{}/net/neoforged/neoforge/maven-metadata.xml", self.maven_url);
+        let metadata = scheduler.client().get(&url).send().await?.error_for_status()?.text().await?;
+        let mut versions: Vec<String> = Vec::new();
+        let mut rest = metadata.as_str();
+        while let Some(start) = rest.find("<version>") {
+            rest = &rest[start + "<version>".len()..];
+            match rest.find("</version>") {
+                Some(end) => {
+                    versions.push(rest[..end].trim().to_string());
+                    rest = &rest[end..];
+                }
+                None => break,
+            }
+        }
+        Ok(versions)
+    }
+
+    // NeoForge versions drop the leading "1." of the game version: 1.20.4 is 20.4.x and 1.21 is 21.0.x.
+    // The newest release wins over betas.
+    pub async fn get_latest_version(&self, scheduler: &DownloadScheduler, game_version: &str) -> Result<String, LauncherError> {
+        let prefix = match game_version.strip_prefix("1.") {
+            Some(version) if version.contains('.') => format!("{}.", version),
+            Some(version) => format!("{}.0.", version),
+            None => return Err(LauncherError::VersionNotFound(format!("neoforge for {}", game_version))),
+        };
+        let versions: Vec<String> = self.get_versions(scheduler).await?.into_iter().filter(|version| version.starts_with(&prefix)).collect();
+        match versions.iter().rev().find(|version| !version.contains('-')).or_else(|| versions.last()) {
+            Some(version) => Ok(version.to_owned()),
+            None => Err(LauncherError::VersionNotFound(format!("neoforge for {}", game_version))),
+        }
+    }
+
+    pub fn version_id(neoforge_version: &str) -> String {
+        format!("neoforge-{}", neoforge_version)
+    }
+
+    pub async fn download_installer(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, neoforge_version: &str) -> Result<PathBuf, LauncherError> {
+        let name = format!("net.neoforged:neoforge:{}:installer", neoforge_version);
+        forge::download_maven_artifact(paths, scheduler, &self.maven_url, &name).await
+    }
+
+    /// Downloads the installer of a NeoForge version and runs it for the given side with `java`.
+    /// Returns the installed version id.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, neoforge_version: &str, side: InstanceType, java: &Path) -> Result<String, LauncherError> {
+        let installer = self.download_installer(paths, scheduler, neoforge_version).await?;
+        forge::run_installer(paths, scheduler, &installer, side, java).await
+    }
+}
+
+impl Default for NeoForgeMeta {
+    fn default() -> Self {
+        NeoForgeMeta::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_paths, TestServer};
+    use crate::minecraft::version::Version;
+    use sha1::{Digest, Sha1};
+    use std::fs::File;
+    use std::io::Write;
+
+    const METADATA_PATH: &str = "/net/neoforged/neoforge/maven-metadata.xml";
+    const INSTALLER_PATH: &str = "/net/neoforged/neoforge/21.0.167/neoforge-21.0.167-installer.jar";
+
+    fn metadata(versions: &[&str]) -> Vec<u8> {
+        let versions: Vec<String> = versions.iter().map(|version| format!("      <version>{}</version>\n", version)).collect();
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<metadata>\n  <groupId>net.neoforged</groupId>\n  <artifactId>neoforge</artifactId>\n  <versioning>\n    <latest>21.1.1</latest>\n    <versions>\n{}    </versions>\n  </versioning>\n</metadata>\n", versions.concat()).into_bytes()
+    }
+
+    fn meta(server: &TestServer) -> NeoForgeMeta {
+        NeoForgeMeta::new().with_maven_url(&server.url("/"))
+    }
+
+    #[tokio::test]
+    async fn versions_are_read_from_the_maven_metadata() {
+        let server = TestServer::start(Vec::from([(METADATA_PATH, metadata(&["20.2.3-beta", "20.4.237", "21.0.167"]))]));
+        let versions = meta(&server).get_versions(&DownloadScheduler::new()).await.unwrap();
+        assert_eq!(versions, Vec::from(["20.2.3-beta", "20.4.237", "21.0.167"]));
+        assert_eq!(server.requests()[0].path, METADATA_PATH);
+    }
+
+    #[tokio::test]
+    async fn game_versions_map_to_neoforge_prefixes() {
+        let server = TestServer::start(Vec::from([(METADATA_PATH, metadata(&["20.4.80-beta", "20.4.237", "20.4.239", "20.4.240-beta", "21.0.160-beta", "21.0.167", "21.1.1", "21.10.5-beta"]))]));
+        let meta = meta(&server);
+        let scheduler = DownloadScheduler::new();
+        assert_eq!(meta.get_latest_version(&scheduler, "1.20.4").await.unwrap(), "20.4.239");
+        assert_eq!(meta.get_latest_version(&scheduler, "1.21").await.unwrap(), "21.0.167");
+        assert_eq!(meta.get_latest_version(&scheduler, "1.21.1").await.unwrap(), "21.1.1");
+        assert_eq!(meta.get_latest_version(&scheduler, "1.21.10").await.unwrap(), "21.10.5-beta");
+        assert!(matches!(meta.get_latest_version(&scheduler, "1.20.1").await, Err(LauncherError::VersionNotFound(_))));
+        assert!(matches!(meta.get_latest_version(&scheduler, "24w14a").await, Err(LauncherError::VersionNotFound(_))));
+    }
+
+    // An installer without processors, so installing the server side needs neither java nor downloads.
+    fn installer() -> Vec<u8> {
+        let profile = serde_json::json!({"spec": 1, "minecraft": "1.21", "json": "/version.json", "data": {}, "processors": [], "libraries": []});
+        let version = serde_json::json!({
+            "id": "neoforge-21.0.167",
+            "inheritsFrom": "1.21",
+            "arguments": {"game": ["--launchTarget", "forgeserver"], "jvm": []},
+            "libraries": [],
+            "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+            "releaseTime": "2024-07-20T00:00:00+00:00",
+            "time": "2024-07-20T00:00:00+00:00",
+            "type": "release"
+        });
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data) in Vec::from([("install_profile.json", profile.to_string()), ("version.json", version.to_string())]).into_iter() {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn installers_run_through_the_forge_installer() {
+        let installer = installer();
+        let sha1 = format!("{:x}", Sha1::digest(&installer));
+        let server = TestServer::start(Vec::from([(INSTALLER_PATH, installer), (&*format!("{}.sha1", INSTALLER_PATH), sha1.into_bytes())]));
+        let paths = temp_paths("neoforge");
+        let vanilla = Version::vanilla_path(&paths, "1.21");
+        std::fs::create_dir_all(vanilla.parent().unwrap()).unwrap();
+        let mut file = File::create(vanilla).unwrap();
+        file.write_all(serde_json::json!({
+            "id": "1.21",
+            "arguments": {"game": [], "jvm": []},
+            "assetIndex": {"id": "17", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "assets": "17",
+            "downloads": {},
+            "libraries": [],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2024-06-13T08:24:03+00:00",
+            "time": "2024-06-13T08:24:03+00:00",
+            "type": "release"
+        }).to_string().as_bytes()).unwrap();
+
+        let server_jar = paths.library("com/mojang/minecraft/1.21/server/1.21.jar");
+        std::fs::create_dir_all(server_jar.parent().unwrap()).unwrap();
+        std::fs::write(server_jar, b"server").unwrap();
+
+        let scheduler = DownloadScheduler::new();
+        let id = meta(&server).install(&paths, &scheduler, "21.0.167", InstanceType::SERVER, Path::new("java")).await.unwrap();
+        assert_eq!(id, NeoForgeMeta::version_id("21.0.167"));
+        assert!(forge::is_installed(&paths, &id, InstanceType::SERVER));
+        assert!(!forge::is_installed(&paths, &id, InstanceType::CLIENT));
+        let version = Version::get_version(&paths, &scheduler, &id).await.unwrap();
+        assert_eq!(version.main_class, "cpw.mods.bootstraplauncher.BootstrapLauncher");
+        assert_eq!(version.jar.as_deref(), Some("1.21"));
+        let requested: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+        assert_eq!(requested, Vec::from([format!("{}.sha1", INSTALLER_PATH), String::from(INSTALLER_PATH)]));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::ChildStdin;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+
+use crate::common::error::LauncherError;
+
+// Lines kept per stream so a subscriber that shows up late (a UI opening the log) sees recent output.
+const OUTPUT_HISTORY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameState {
+    Running,
+    Exited {
+        code: Option<i32>,
+    },
+    Killed,
+}
+
+// One of the output streams of the game. Each subscriber gets the recent history followed by every new line.
+#[derive(Debug, Default)]
+struct OutputLines {
+    history: VecDeque<String>,
+    subscribers: Vec<UnboundedSender<String>>,
+    closed: bool,
+}
+
+/// The standard input of a process started with a console, such as a server. Clones write to the same pipe.
+#[derive(Debug, Clone)]
+pub struct GameConsole {
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+}
+
+/// A running game. Dropping the handle leaves the game running; use [`GameProcess::kill`] to stop it.
+pub struct GameProcess {
+    pid: Option<u32>,
+    started_at: SystemTime,
+    stopped_at: Arc<Mutex<Option<SystemTime>>>,
+    state: watch::Receiver<GameState>,
+    stdout: Arc<Mutex<OutputLines>>,
+    stderr: Arc<Mutex<OutputLines>>,
+    kill: Arc<Notify>,
+    console: Option<GameConsole>,
+}
+
+impl GameState {
+    pub fn is_running(&self) -> bool {
+        *self == GameState::Running
+    }
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameState::Running => write!(f, "running"),
+            GameState::Exited { code: Some(code) } => write!(f, "exited with code {}", code),
+            GameState::Exited { code: None } => write!(f, "exited"),
+            GameState::Killed => write!(f, "killed"),
+        }
+    }
+}
+
+impl OutputLines {
+    fn push(&mut self, line: String) {
+        self.subscribers.retain(|subscriber| subscriber.send(line.to_owned()).is_ok());
+        if self.history.len() == OUTPUT_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+
+    fn subscribe(&mut self) -> UnboundedReceiver<String> {
+        let (sender, receiver) = unbounded_channel();
+        for line in &self.history {
+            let _ = sender.send(line.to_owned());
+        }
+        if !self.closed {
+            self.subscribers.push(sender);
+        }
+        receiver
+    }
+
+    // Ends the streams of the subscribers once the game closed its output.
+    fn close(&mut self) {
+        self.closed = true;
+        self.subscribers.clear();
+    }
+}
+
+impl GameConsole {
+    /// Writes one line, such as a server command, to the standard input of the process.
+    pub async fn send_line(&self, line: &str) -> Result<(), LauncherError> {
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(format!("{}\n", line).as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+}
+
+impl GameProcess {
+    /// Spawns `command` with piped stdout and stderr and supervises it on the tokio runtime.
+    pub fn spawn(command: tokio::process::Command) -> Result<GameProcess, LauncherError> {
+        GameProcess::start(command, false)
+    }
+
+    /// Like [`GameProcess::spawn`], with standard input piped to a [`GameConsole`].
+    pub fn spawn_with_console(command: tokio::process::Command) -> Result<GameProcess, LauncherError> {
+        GameProcess::start(command, true)
+    }
+
+    fn start(mut command: tokio::process::Command, console: bool) -> Result<GameProcess, LauncherError> {
+        let stdin = if console { std::process::Stdio::piped() } else { std::process::Stdio::null() };
+        command.stdin(stdin).stdout(std::process::Stdio::piped()).stderr(std::process::Stdio::piped());
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => return Err(LauncherError::Launch(format!("Unable to start the game: {}", e))),
+        };
+        let console = child.stdin.take().map(|stdin| GameConsole {
+            stdin: Arc::new(tokio::sync::Mutex::new(stdin)),
+        });
+        let stdout: Arc<Mutex<OutputLines>> = Arc::new(Mutex::new(OutputLines::default()));
+        let stderr: Arc<Mutex<OutputLines>> = Arc::new(Mutex::new(OutputLines::default()));
+        let mut readers: Vec<JoinHandle<()>> = Vec::new();
+        if let Some(pipe) = child.stdout.take() {
+            readers.push(tokio::spawn(read_lines(pipe, Arc::clone(&stdout))));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            readers.push(tokio::spawn(read_lines(pipe, Arc::clone(&stderr))));
+        }
+
+        let (state_sender, state) = watch::channel(GameState::Running);
+        let stopped_at: Arc<Mutex<Option<SystemTime>>> = Arc::new(Mutex::new(None));
+        let kill = Arc::new(Notify::new());
+        let pid = child.id();
+        let supervisor_stopped_at = Arc::clone(&stopped_at);
+        let supervisor_kill = Arc::clone(&kill);
+        tokio::spawn(async move {
+            let state = tokio::select! {
+                status = child.wait() => GameState::Exited { code: status.ok().and_then(|status| status.code()) },
+                _ = supervisor_kill.notified() => {
+                    if let Err(e) = child.kill().await {
+                        log::warn!("Unable to kill the game: {}", e);
+                    }
+                    GameState::Killed
+                }
+            };
+            // Every line is delivered before the state changes.
+            for reader in readers {
+                let _ = reader.await;
+            }
+            *supervisor_stopped_at.lock().unwrap() = Some(SystemTime::now());
+            log::info!("Game {}", state);
+            let _ = state_sender.send(state);
+        });
+
+        Ok(GameProcess {
+            pid,
+            started_at: SystemTime::now(),
+            stopped_at,
+            state,
+            stdout,
+            stderr,
+            kill,
+            console,
+        })
+    }
+
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    pub fn stopped_at(&self) -> Option<SystemTime> {
+        *self.stopped_at.lock().unwrap()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state.borrow().clone()
+    }
+
+    // Notified on every state change, from running to exited or killed.
+    pub fn watch_state(&self) -> watch::Receiver<GameState> {
+        self.state.clone()
+    }
+
+    pub fn stdout(&self) -> UnboundedReceiver<String> {
+        self.stdout.lock().unwrap().subscribe()
+    }
+
+    pub fn stderr(&self) -> UnboundedReceiver<String> {
+        self.stderr.lock().unwrap().subscribe()
+    }
+
+    // None unless the process was started with spawn_with_console.
+    pub fn console(&self) -> Option<GameConsole> {
+        self.console.clone()
+    }
+
+    /// Kills the game. The state turns to [`GameState::Killed`] once it is gone.
+    pub fn kill(&self) {
+        self.kill.notify_one();
+    }
+
+    /// Waits until the game is no longer running and returns how it ended.
+    pub async fn wait(&self) -> GameState {
+        let mut state = self.state.clone();
+        loop {
+            let current = state.borrow_and_update().clone();
+            if !current.is_running() {
+                return current;
+            }
+            if state.changed().await.is_err() {
+                return state.borrow().clone();
+            }
+        }
+    }
+}
+
+async fn read_lines<R: AsyncRead + Unpin>(pipe: R, output: Arc<Mutex<OutputLines>>) {
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        output.lock().unwrap().push(line);
+    }
+    output.lock().unwrap().close();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn console_lines_reach_the_process() {
+        let game = GameProcess::spawn_with_console(tokio::process::Command::new("cat")).unwrap();
+        let mut stdout = game.stdout();
+        let console = game.console().unwrap();
+        console.send_line("say hello").await.unwrap();
+        assert_eq!(stdout.recv().await.unwrap(), "say hello");
+        game.kill();
+        assert_eq!(game.wait().await, GameState::Killed);
+    }
+
+    #[tokio::test]
+    async fn output_and_exit_code_are_reported() {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c").arg("echo out; echo err >&2; exit 3");
+        let game = GameProcess::spawn(command).unwrap();
+        assert!(game.console().is_none());
+        assert_eq!(game.wait().await, GameState::Exited { code: Some(3) });
+        assert_eq!(game.stdout().recv().await.unwrap(), "out");
+        assert_eq!(game.stderr().recv().await.unwrap(), "err");
+        assert!(game.stopped_at().is_some());
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+pub const FEATURE_DEMO_USER: &str = "is_demo_user";
+pub const FEATURE_CUSTOM_RESOLUTION: &str = "has_custom_resolution";
+pub const FEATURE_QUICK_PLAYS_SUPPORT: &str = "has_quick_plays_support";
+pub const FEATURE_QUICK_PLAY_SINGLEPLAYER: &str = "is_quick_play_singleplayer";
+pub const FEATURE_QUICK_PLAY_MULTIPLAYER: &str = "is_quick_play_multiplayer";
+pub const FEATURE_QUICK_PLAY_REALMS: &str = "is_quick_play_realms";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    pub os: Option<RuleOs>,
+    pub features: Option<HashMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+// Every field is a regular expression; name and arch have to match in full, version is searched.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleOs {
+    pub name: Option<String>,
+    pub arch: Option<String>,
+    pub version: Option<String>,
+}
+
+// What rules are evaluated against: the platform in Mojang's naming and the enabled launcher features.
+#[derive(Debug, Clone)]
+pub struct RuleContext {
+    pub os_name: String,
+    pub os_arch: String,
+    pub os_version: String,
+    pub features: HashMap<String, bool>,
+}
+
+impl Rule {
+    pub fn matches(&self, context: &RuleContext) -> bool {
+        if let Some(os) = &self.os {
+            if !os.matches(context) {
+                return false;
+            }
+        }
+        if let Some(features) = &self.features {
+            for (feature, enabled) in features {
+                if context.has_feature(feature) != *enabled {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl RuleOs {
+    pub fn matches(&self, context: &RuleContext) -> bool {
+        if let Some(name) = &self.name {
+            if !regex_matches(&format!("^(?:{})$", name), &context.os_name) {
+                return false;
+            }
+        }
+        if let Some(arch) = &self.arch {
+            if !regex_matches(&format!("^(?:{})$", arch), &context.os_arch) {
+                return false;
+            }
+        }
+        if let Some(version) = &self.version {
+            if !regex_matches(version, &context.os_version) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl RuleContext {
+    pub fn new(os_name: &str, os_arch: &str, os_version: &str) -> RuleContext {
+        RuleContext {
+            os_name: os_name.to_string(),
+            os_arch: os_arch.to_string(),
+            os_version: os_version.to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    // The running platform with every feature disabled. The OS version is probed once per process.
+    pub fn current() -> RuleContext {
+        static CURRENT: OnceLock<RuleContext> = OnceLock::new();
+        CURRENT.get_or_init(|| RuleContext::new(current_os_name(), current_os_arch(), &current_os_version())).clone()
+    }
+
+    pub fn with_feature(mut self, feature: &str, enabled: bool) -> RuleContext {
+        self.features.insert(feature.to_string(), enabled);
+        self
+    }
+
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.get(feature).copied().unwrap_or(false)
+    }
+}
+
+/// Mojang's rule semantics: without rules everything is allowed, otherwise the action of the last
+/// matching rule wins and nothing is allowed if no rule matches.
+pub fn is_allowed(rules: Option<&Vec<Rule>>, context: &RuleContext) -> bool {
+    let rules = match rules {
+        Some(rules) if !rules.is_empty() => rules,
+        _ => return true,
+    };
+    let mut allowed = false;
+    for rule in rules {
+        if rule.matches(context) {
+            allowed = rule.action == RuleAction::Allow;
+        }
+    }
+    allowed
+}
+
+fn regex_matches(pattern: &str, value: &str) -> bool {
+    match Regex::new(pattern) {
+        Ok(regex) => regex.is_match(value),
+        Err(e) => {
+            log::warn!("Ignoring invalid rule pattern {}: {}", pattern, e);
+            false
+        }
+    }
+}
+
+pub fn current_os_name() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "osx",
+        os => os,
+    }
+}
+
+// Java's os.arch naming, which is what the version manifests match against.
+pub fn current_os_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "x86",
+        "x86_64" if cfg!(target_os = "macos") => "x86_64",
+        "x86_64" => "amd64",
+        "aarch64" => "aarch64",
+        "arm" => "arm",
+        arch => arch,
+    }
+}
+
+fn current_os_version() -> String {
+    let output = if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd").args(["/C", "ver"]).output()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("sw_vers").arg("-productVersion").output()
+    } else {
+        std::process::Command::new("uname").arg("-r").output()
+    };
+    let output = match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+        Err(e) => {
+            log::debug!("Unable to determine the OS version: {}", e);
+            return String::new();
+        }
+    };
+    // Windows prints "Microsoft Windows [Version 10.0.19042.804]".
+    match output.find("Version ") {
+        Some(index) => output[index + "Version ".len()..].trim_end_matches(|c: char| !c.is_ascii_digit()).to_string(),
+        None => output.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> Vec<Rule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn linux() -> RuleContext {
+        RuleContext::new("linux", "amd64", "5.10.0-generic")
+    }
+
+    #[test]
+    fn no_rules_allow_everything() {
+        assert!(is_allowed(None, &linux()));
+        assert!(is_allowed(Some(&Vec::new()), &linux()));
+    }
+
+    #[test]
+    fn nothing_is_allowed_without_a_matching_rule() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "osx"}}]"#);
+        assert!(!is_allowed(Some(&rules), &linux()));
+        assert!(is_allowed(Some(&rules), &RuleContext::new("osx", "x86_64", "11.2")));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = rules(r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#);
+        assert!(is_allowed(Some(&rules), &linux()));
+        assert!(!is_allowed(Some(&rules), &RuleContext::new("osx", "x86_64", "11.2")));
+    }
+
+    #[test]
+    fn os_name_and_arch_match_in_full() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "linux|osx", "arch": "x86"}}]"#);
+        assert!(is_allowed(Some(&rules), &RuleContext::new("linux", "x86", "")));
+        assert!(!is_allowed(Some(&rules), &RuleContext::new("linux", "x86_64", "")));
+        assert!(!is_allowed(Some(&rules), &RuleContext::new("linuxbsd", "x86", "")));
+    }
+
+    #[test]
+    fn os_version_is_searched() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}]"#);
+        assert!(is_allowed(Some(&rules), &RuleContext::new("windows", "amd64", "10.0.19042.804")));
+        assert!(!is_allowed(Some(&rules), &RuleContext::new("windows", "amd64", "6.1.7601")));
+    }
+
+    #[test]
+    fn invalid_patterns_do_not_match() {
+        let rules = rules(r#"[{"action": "allow", "os": {"name": "("}}]"#);
+        assert!(!is_allowed(Some(&rules), &linux()));
+    }
+
+    #[test]
+    fn features_have_to_match_their_state() {
+        let rules = rules(r#"[{"action": "allow", "features": {"has_custom_resolution": true}}]"#);
+        assert!(!is_allowed(Some(&rules), &linux()));
+        assert!(is_allowed(Some(&rules), &linux().with_feature(FEATURE_CUSTOM_RESOLUTION, true)));
+
+        let rules = self::rules(r#"[{"action": "allow", "features": {"is_demo_user": false}}]"#);
+        assert!(is_allowed(Some(&rules), &linux()));
+        assert!(!is_allowed(Some(&rules), &linux().with_feature(FEATURE_DEMO_USER, true)));
+    }
+}
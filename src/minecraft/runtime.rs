@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::java;
+
+pub const JAVA_RUNTIMES: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+pub const JAVA_RUNTIMES_ENV: &str = "DUCK_LAUNCHER_JAVA_RUNTIMES";
+// Versions without a javaVersion run on the Java 8 runtime.
+pub const LEGACY_COMPONENT: &str = "jre-legacy";
+
+// Client for a Mojang-style Java runtime index: platform -> component -> runtime listings, each
+// pointing at a manifest of the files of the runtime.
+#[derive(Debug, Clone)]
+pub struct RuntimeMeta {
+    manifest_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuntimeListing {
+    pub manifest: RuntimeDownload,
+    pub version: RuntimeVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuntimeDownload {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuntimeVersion {
+    pub name: String,
+    pub released: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuntimeManifest {
+    pub files: BTreeMap<String, RuntimeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Directory,
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RuntimeFileDownloads {
+    pub raw: RuntimeDownload,
+}
+
+impl RuntimeMeta {
+    pub fn new() -> RuntimeMeta {
+        RuntimeMeta {
+            manifest_url: JAVA_RUNTIMES.to_string(),
+        }
+    }
+
+    // The index named by DUCK_LAUNCHER_JAVA_RUNTIMES, or Mojang's.
+    pub fn from_env() -> RuntimeMeta {
+        match std::env::var(JAVA_RUNTIMES_ENV) {
+            Ok(manifest_url) if !manifest_url.is_empty() => RuntimeMeta::new().with_manifest_url(&manifest_url),
+            _ => RuntimeMeta::new(),
+        }
+    }
+
+    pub fn with_manifest_url(mut self, manifest_url: &str) -> RuntimeMeta {
+        self.manifest_url = manifest_url.to_string();
+        self
+    }
+
+    pub fn manifest_url(&self) -> &str {
+        &self.manifest_url
+    }
+
+    /// The runtime listings of every component for the running platform.
+    pub async fn get_components(&self, scheduler: &DownloadScheduler) -> Result<HashMap<String, Vec<RuntimeListing>>, LauncherError> {
+        let platform = match current_platform() {
+            Some(platform) => platform,
+            None => return Err(LauncherError::VersionNotFound(format!("Java runtimes for {} {}", std::env::consts::OS, std::env::consts::ARCH))),
+        };
+        let mut platforms: HashMap<String, HashMap<String, Vec<RuntimeListing>>> = scheduler.client().get(&self.manifest_url).send().await?.error_for_status()?.json().await?;
+        Ok(platforms.remove(platform).unwrap_or_default())
+    }
+
+    /// Installs a runtime component such as java-runtime-gamma under runtime/<component>, downloading
+    /// missing or corrupt files and restoring executable bits and symlinks. Returns its Java executable.
+    pub async fn install(&self, paths: &LauncherPaths, scheduler: &DownloadScheduler, component: &str) -> Result<PathBuf, LauncherError> {
+        let mut components = self.get_components(scheduler).await?;
+        let listing = match components.remove(component).and_then(|listings| listings.into_iter().next()) {
+            Some(listing) => listing,
+            None => return Err(LauncherError::VersionNotFound(format!("Java runtime {}", component))),
+        };
+        let manifest_path: PathBuf = paths.join(Vec::from(["meta", "runtime", &format!("{}.json", component)]));
+        let manifest_task = DownloadTask::new(&listing.manifest.url, manifest_path.to_owned()).with_sha1(&listing.manifest.sha1).with_size(listing.manifest.size);
+        if !scheduler.is_valid(&manifest_task) {
+            scheduler.download(manifest_task).await?;
+        }
+        let mut file = File::open(&manifest_path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        let manifest: RuntimeManifest = serde_json::from_str(&data)?;
+
+        let home: PathBuf = paths.join(Vec::from(["runtime", component]));
+        let mut tasks: Vec<DownloadTask> = Vec::new();
+        for (name, file) in &manifest.files {
+            let path: PathBuf = home.join(name);
+            match file {
+                RuntimeFile::File { downloads, .. } => tasks.push(DownloadTask::new(&downloads.raw.url, path).with_sha1(&downloads.raw.sha1).with_size(downloads.raw.size)),
+                RuntimeFile::Directory => std::fs::create_dir_all(path)?,
+                RuntimeFile::Link { .. } => {}
+            }
+        }
+        scheduler.submit_invalid(&format!("runtime {}", component), tasks);
+        let report = scheduler.run().await;
+        if !report.is_success() {
+            return Err(LauncherError::Download(report));
+        }
+        for (name, file) in &manifest.files {
+            let path: PathBuf = home.join(name);
+            match file {
+                RuntimeFile::File { executable: true, .. } => set_executable(&path)?,
+                RuntimeFile::Link { target } => create_link(&path, target)?,
+                _ => {}
+            }
+        }
+        log::info!("Installed Java runtime {} {}", component, listing.version.name);
+        Ok(runtime_executable(paths, component))
+    }
+}
+
+impl Default for RuntimeMeta {
+    fn default() -> Self {
+        RuntimeMeta::new()
+    }
+}
+
+// The platform keys of Mojang's runtime index.
+pub fn current_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+// macOS runtimes are bundles with the Java home inside.
+pub fn runtime_executable(paths: &LauncherPaths, component: &str) -> PathBuf {
+    let home: PathBuf = paths.join(Vec::from(["runtime", component]));
+    if cfg!(target_os = "macos") {
+        java::java_executable(&home.join("jre.bundle").join("Contents").join("Home"))
+    } else {
+        java::java_executable(&home)
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<(), LauncherError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<(), LauncherError> {
+    Ok(())
+}
+
+// Link targets are relative to the directory of the link.
+#[cfg(unix)]
+fn create_link(path: &Path, target: &str) -> Result<(), LauncherError> {
+    if std::fs::symlink_metadata(path).is_ok() {
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_link(path: &Path, target: &str) -> Result<(), LauncherError> {
+    log::debug!("Skipping link {} -> {}", path.display(), target);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_support::{temp_paths, TestServer};
+    use sha1::{Digest, Sha1};
+
+    const JAVA: &[u8] = b"#!/bin/sh\necho java\n";
+
+    fn sha1(data: &[u8]) -> String {
+        format!("{:x}", Sha1::digest(data))
+    }
+
+    fn server() -> TestServer {
+        TestServer::start_with(|base_url| {
+            let manifest = serde_json::json!({
+                "files": {
+                    "bin": {"type": "directory"},
+                    "bin/java": {"type": "file", "executable": true, "downloads": {"raw": {"sha1": sha1(JAVA), "size": JAVA.len(), "url": format!("{}/files/java", base_url)}}},
+                    "lib": {"type": "directory"},
+                    "lib/java": {"type": "link", "target": "../bin/java"}
+                }
+            })
+            .to_string();
+            let index = serde_json::json!({
+                current_platform().unwrap(): {
+                    "java-runtime-alpha": [{
+                        "manifest": {"sha1": sha1(manifest.as_bytes()), "size": manifest.len(), "url": format!("{}/manifest.json", base_url)},
+                        "version": {"name": "16.0.1.9.1", "released": "2021-05-10T16:43:02+00:00"}
+                    }]
+                }
+            })
+            .to_string();
+            Vec::from([("/all.json".to_string(), index.into_bytes()), ("/manifest.json".to_string(), manifest.into_bytes()), ("/files/java".to_string(), JAVA.to_vec())])
+        })
+    }
+
+    #[tokio::test]
+    async fn lists_components_of_the_current_platform() {
+        let server = server();
+        let runtimes = RuntimeMeta::new().with_manifest_url(&server.url("/all.json"));
+        let components = runtimes.get_components(&DownloadScheduler::new()).await.unwrap();
+        assert_eq!(components.keys().collect::<Vec<&String>>(), Vec::from(["java-runtime-alpha"]));
+        assert_eq!(components["java-runtime-alpha"][0].version.name, "16.0.1.9.1");
+    }
+
+    #[tokio::test]
+    async fn installs_files_executable_bits_and_links() {
+        let server = server();
+        let paths = temp_paths("runtime");
+        let runtimes = RuntimeMeta::new().with_manifest_url(&server.url("/all.json"));
+        let java = runtimes.install(&paths, &DownloadScheduler::new(), "java-runtime-alpha").await.unwrap();
+        assert_eq!(java, runtime_executable(&paths, "java-runtime-alpha"));
+        let home = paths.join(Vec::from(["runtime", "java-runtime-alpha"]));
+        assert_eq!(std::fs::read(home.join("bin").join("java")).unwrap(), JAVA);
+        assert!(paths.join(Vec::from(["meta", "runtime", "java-runtime-alpha.json"])).is_file());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(home.join("bin").join("java")).unwrap().permissions().mode() & 0o111, 0o111);
+            assert_eq!(std::fs::read_link(home.join("lib").join("java")).unwrap(), PathBuf::from("../bin/java"));
+        }
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_component_is_not_found() {
+        let server = server();
+        let paths = temp_paths("runtime");
+        let runtimes = RuntimeMeta::new().with_manifest_url(&server.url("/all.json"));
+        let result = runtimes.install(&paths, &DownloadScheduler::new(), "java-runtime-omega").await;
+        assert!(matches!(result, Err(LauncherError::VersionNotFound(_))));
+        let _ = std::fs::remove_dir_all(paths.root());
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::common::error::LauncherError;
+
+// Every `${name}` placeholder Mojang's version manifests use in launch arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Placeholder {
+    AuthPlayerName,
+    AuthUuid,
+    AuthAccessToken,
+    AuthSession,
+    AuthXuid,
+    ClientId,
+    UserType,
+    UserProperties,
+    VersionName,
+    VersionType,
+    GameDirectory,
+    GameAssets,
+    AssetsRoot,
+    AssetsIndexName,
+    ResolutionWidth,
+    ResolutionHeight,
+    NativesDirectory,
+    LibraryDirectory,
+    Classpath,
+    ClasspathSeparator,
+    LauncherName,
+    LauncherVersion,
+    QuickPlayPath,
+    QuickPlaySingleplayer,
+    QuickPlayMultiplayer,
+    QuickPlayRealms,
+}
+
+const PLACEHOLDERS: [(Placeholder, &str); 26] = [
+    (Placeholder::AuthPlayerName, "auth_player_name"),
+    (Placeholder::AuthUuid, "auth_uuid"),
+    (Placeholder::AuthAccessToken, "auth_access_token"),
+    (Placeholder::AuthSession, "auth_session"),
+    (Placeholder::AuthXuid, "auth_xuid"),
+    (Placeholder::ClientId, "clientid"),
+    (Placeholder::UserType, "user_type"),
+    (Placeholder::UserProperties, "user_properties"),
+    (Placeholder::VersionName, "version_name"),
+    (Placeholder::VersionType, "version_type"),
+    (Placeholder::GameDirectory, "game_directory"),
+    (Placeholder::GameAssets, "game_assets"),
+    (Placeholder::AssetsRoot, "assets_root"),
+    (Placeholder::AssetsIndexName, "assets_index_name"),
+    (Placeholder::ResolutionWidth, "resolution_width"),
+    (Placeholder::ResolutionHeight, "resolution_height"),
+    (Placeholder::NativesDirectory, "natives_directory"),
+    (Placeholder::LibraryDirectory, "library_directory"),
+    (Placeholder::Classpath, "classpath"),
+    (Placeholder::ClasspathSeparator, "classpath_separator"),
+    (Placeholder::LauncherName, "launcher_name"),
+    (Placeholder::LauncherVersion, "launcher_version"),
+    (Placeholder::QuickPlayPath, "quickPlayPath"),
+    (Placeholder::QuickPlaySingleplayer, "quickPlaySingleplayer"),
+    (Placeholder::QuickPlayMultiplayer, "quickPlayMultiplayer"),
+    (Placeholder::QuickPlayRealms, "quickPlayRealms"),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVariables {
+    values: HashMap<Placeholder, String>,
+}
+
+impl Placeholder {
+    pub fn name(&self) -> &'static str {
+        PLACEHOLDERS.iter().find(|(placeholder, _)| placeholder == self).map(|(_, name)| *name).unwrap_or_default()
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = LauncherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match PLACEHOLDERS.iter().find(|(_, name)| name.eq(&s)) {
+            Some((placeholder, _)) => Ok(*placeholder),
+            None => Err(LauncherError::Template(format!("unknown placeholder ${{{}}}", s))),
+        }
+    }
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${{{}}}", self.name())
+    }
+}
+
+impl TemplateVariables {
+    pub fn new() -> TemplateVariables {
+        TemplateVariables {
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, placeholder: Placeholder, value: &str) -> TemplateVariables {
+        self.values.insert(placeholder, value.to_string());
+        self
+    }
+
+    pub fn set(&mut self, placeholder: Placeholder, value: &str) {
+        self.values.insert(placeholder, value.to_string());
+    }
+
+    pub fn get(&self, placeholder: Placeholder) -> Option<&str> {
+        self.values.get(&placeholder).map(|value| value.as_str())
+    }
+
+    /// Substitutes every `${name}` in `template`, wherever it appears in the string. Unknown
+    /// placeholders and placeholders without a value are errors; a `${` without a closing brace is
+    /// kept as it is.
+    pub fn render(&self, template: &str) -> Result<String, LauncherError> {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("${") {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            rendered.push_str(&rest[..start]);
+            let placeholder: Placeholder = rest[start + 2..end].parse()?;
+            match self.get(placeholder) {
+                Some(value) => rendered.push_str(value),
+                None => return Err(LauncherError::Template(format!("no value for {}", placeholder))),
+            }
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    pub fn render_all(&self, templates: &[String]) -> Result<Vec<String>, LauncherError> {
+        templates.iter().map(|template| self.render(template)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> TemplateVariables {
+        TemplateVariables::new()
+            .with(Placeholder::AuthPlayerName, "Duck")
+            .with(Placeholder::NativesDirectory, "/natives")
+            .with(Placeholder::VersionName, "1.16.5")
+    }
+
+    #[test]
+    fn renders_whole_arguments() {
+        assert_eq!(variables().render("${auth_player_name}").unwrap(), "Duck");
+        assert_eq!(variables().render("--demo").unwrap(), "--demo");
+    }
+
+    #[test]
+    fn renders_embedded_placeholders() {
+        assert_eq!(variables().render("-Djava.library.path=${natives_directory}").unwrap(), "-Djava.library.path=/natives");
+        assert_eq!(variables().render("${auth_player_name}@${version_name}!").unwrap(), "Duck@1.16.5!");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_errors() {
+        assert!(matches!(variables().render("${no_such_thing}"), Err(LauncherError::Template(_))));
+    }
+
+    #[test]
+    fn placeholders_without_value_are_errors() {
+        assert!(matches!(variables().render("--width ${resolution_width}"), Err(LauncherError::Template(_))));
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_kept() {
+        assert_eq!(variables().render("${version_name}-${natives").unwrap(), "1.16.5-${natives");
+    }
+
+    #[test]
+    fn placeholder_names_round_trip() {
+        for (placeholder, name) in PLACEHOLDERS.iter() {
+            assert_eq!(name.parse::<Placeholder>().unwrap(), *placeholder);
+            assert_eq!(placeholder.to_string(), format!("${{{}}}", name));
+        }
+    }
+
+    #[test]
+    fn renders_quick_play_arguments() {
+        let variables = variables().with(Placeholder::QuickPlayMultiplayer, "play.example.com:25565");
+        assert_eq!(variables.render("${quickPlayMultiplayer}").unwrap(), "play.example.com:25565");
+        assert!(matches!(variables.render("${quickPlayPath}"), Err(LauncherError::Template(_))));
+    }
+
+    #[test]
+    fn render_all_stops_at_the_first_error() {
+        let templates = Vec::from([String::from("${auth_player_name}"), String::from("${auth_uuid}")]);
+        assert!(variables().render_all(&templates[..1]).is_ok());
+        assert!(variables().render_all(&templates).is_err());
+    }
+}
@@ -6,9 +6,15 @@ use std::path::PathBuf;
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::common;
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
+use crate::minecraft::rule;
+use crate::minecraft::rule::{Rule, RuleContext};
 use crate::minecraft::version_manifest::{VersionManifest, VersionManifestVersion};
 
+const LEGACY_JVM_ARGUMENTS: [&str; 3] = ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"];
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Version {
@@ -18,6 +24,9 @@ pub struct Version {
     pub compliance_level: Option<u64>,
     pub downloads: VersionDownload,
     pub id: String,
+    pub inherits_from: Option<String>,
+    pub jar: Option<String>,
+    pub java_version: Option<VersionJavaVersion>,
     pub libraries: Vec<VersionLibrary>,
     pub logging: Option<Value>,
     //todo:
@@ -31,8 +40,34 @@ pub struct Version {
 
 #[derive(Debug, Deserialize)]
 pub struct VersionArgument {
-    pub game: Vec<Value>,
-    pub jvm: Vec<Value>,
+    #[serde(default)]
+    pub game: Vec<VersionArgumentValue>,
+    #[serde(default)]
+    pub jvm: Vec<VersionArgumentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum VersionArgumentValue {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: VersionArgumentValues,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum VersionArgumentValues {
+    Single(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJavaVersion {
+    pub component: String,
+    pub major_version: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,8 +100,18 @@ pub struct VersionLibrary {
     pub downloads: Option<VersionLibraryDownload>,
     pub name: String,
     pub url: Option<String>,//maven link
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
     pub natives: Option<HashMap<String, String>>,
-    pub rules: Option<Vec<VersionLibraryRule>>,
+    pub extract: Option<VersionLibraryExtract>,
+    pub rules: Option<Vec<Rule>>,
+}
+
+// Entries of a natives jar that are not extracted, matched by prefix (usually META-INF/).
+#[derive(Debug, Deserialize)]
+pub struct VersionLibraryExtract {
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,54 +128,395 @@ pub struct VersionLibraryDownloadObject {
     pub url: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct VersionLibraryRule{
-    pub action: String,
-    pub os: Option<VersionLibraryRuleOSObject>
-}
-
-#[derive(Debug, Deserialize)]
-pub struct VersionLibraryRuleOSObject{
-    pub name: String,
-    pub version: Option<String>
-}
 
 impl Version{
-    pub async fn get_version(version: &str) -> Result<Option<Version>, std::io::Error> {
-        let path: PathBuf = common::join_directories(Vec::from(["meta", "com", "mojang", "minecraft", &version, &*format!("{}.json", version)])).unwrap();
-        if path.exists() {
-            return Version::read(&path);
-        } else {
-            match VersionManifest::get(true).await {
-                Ok(val) => {
-                    for ver in val.versions {
-                        if ver.id.eq(version) {
-                            match Version::fetch(ver).await {
-                                Ok(()) => return Version::read(&path),
-                                Err(e) => panic!("{}", e)
-                            }
-                        }
-                    }
+    /// Loads a version and every version it inherits from, preferring JSONs already under meta/
+    /// and fetching vanilla versions from the manifest otherwise, then merges the chain into one.
+    pub async fn get_version(paths: &LauncherPaths, scheduler: &DownloadScheduler, version: &str) -> Result<Version, LauncherError> {
+        let mut chain: Vec<Value> = Vec::new();
+        let mut next: Option<String> = Some(version.to_string());
+        while let Some(id) = next {
+            let value = match Version::local_path(paths, &id) {
+                Some(path) => Version::read_value(&path)?,
+                None => Version::fetch_value(paths, scheduler, &id).await?,
+            };
+            next = Version::parent_of(&chain, &id, &value)?;
+            chain.push(value);
+        }
+        Version::merge_chain(chain)
+    }
+
+    // Same as get_version, but only from the JSONs already under meta/.
+    pub fn read_installed(paths: &LauncherPaths, version: &str) -> Result<Version, LauncherError> {
+        let mut chain: Vec<Value> = Vec::new();
+        let mut next: Option<String> = Some(version.to_string());
+        while let Some(id) = next {
+            let value = match Version::local_path(paths, &id) {
+                Some(path) => Version::read_value(&path)?,
+                None => return Err(LauncherError::VersionNotFound(id)),
+            };
+            next = Version::parent_of(&chain, &id, &value)?;
+            chain.push(value);
+        }
+        Version::merge_chain(chain)
+    }
+
+    pub fn installed(paths: &LauncherPaths) -> Result<Vec<String>, LauncherError> {
+        let mut versions: Vec<String> = Vec::new();
+        for path in [paths.join(Vec::from(["meta", "versions"])), paths.join(Vec::from(["meta", "com", "mojang", "minecraft"]))] {
+            if !path.exists() {
+                continue;
+            }
+            for entry in std::fs::read_dir(path)? {
+                let entry = entry?;
+                let id = entry.file_name().to_string_lossy().to_string();
+                if entry.path().join(format!("{}.json", id)).is_file() && !versions.contains(&id) {
+                    versions.push(id);
                 }
-                Err(e) => panic!("{}", e)
             }
         }
-        Ok(None)
+        versions.sort();
+        Ok(versions)
     }
 
-    fn read(path: &PathBuf) -> Result<Option<Version>, std::io::Error> {
+    // Custom and modded versions live in meta/versions/<id>/<id>.json and win over vanilla ones.
+    pub fn custom_path(paths: &LauncherPaths, version: &str) -> PathBuf {
+        paths.join(Vec::from(["meta", "versions", version, &*format!("{}.json", version)]))
+    }
+
+    pub fn vanilla_path(paths: &LauncherPaths, version: &str) -> PathBuf {
+        paths.join(Vec::from(["meta", "com", "mojang", "minecraft", version, &*format!("{}.json", version)]))
+    }
+
+    fn local_path(paths: &LauncherPaths, version: &str) -> Option<PathBuf> {
+        Vec::from([Version::custom_path(paths, version), Version::vanilla_path(paths, version)]).into_iter().find(|path| path.is_file())
+    }
+
+    fn read_value(path: &PathBuf) -> Result<Value, LauncherError> {
         let mut file = File::open(path)?;
         let mut data = String::new();
-        file.read_to_string(&mut data).expect("Unable to read file");
-        let version: Version = serde_json::from_str(&data).expect("JSON was not well-formatted");
-        Ok(Some(version))
+        file.read_to_string(&mut data)?;
+        let value: Value = serde_json::from_str(&data)?;
+        Ok(value)
+    }
+
+    async fn fetch_value(paths: &LauncherPaths, scheduler: &DownloadScheduler, version: &str) -> Result<Value, LauncherError> {
+        let manifest = VersionManifest::get(paths, scheduler, true).await?;
+        for ver in manifest.versions {
+            if ver.id.eq(version) {
+                Version::fetch(paths, scheduler, ver).await?;
+                return Version::read_value(&Version::vanilla_path(paths, version));
+            }
+        }
+        Err(LauncherError::VersionNotFound(version.to_string()))
+    }
+
+    fn parent_of(chain: &[Value], id: &str, value: &Value) -> Result<Option<String>, LauncherError> {
+        let parent = value.get("inheritsFrom").and_then(|parent| parent.as_str()).map(String::from);
+        if let Some(parent) = &parent {
+            let seen = chain.iter().any(|version| version.get("id").and_then(|id| id.as_str()) == Some(parent.as_str()));
+            if seen || parent.eq(id) {
+                return Err(LauncherError::VersionNotFound(format!("{} (inheritance cycle through {})", id, parent)));
+            }
+        }
+        Ok(parent)
+    }
+
+    // Merges from the root of the chain down, so each child is applied on top of its parent.
+    fn merge_chain(mut chain: Vec<Value>) -> Result<Version, LauncherError> {
+        let mut merged = match chain.pop() {
+            Some(root) => root,
+            None => return Err(LauncherError::VersionNotFound(String::new())),
+        };
+        while let Some(child) = chain.pop() {
+            merged = Version::merge(merged, child);
+        }
+        let version: Version = serde_json::from_value(merged)?;
+        Ok(version)
+    }
+
+    // The vanilla launcher's semantics: the child's libraries come first, argument lists are
+    // appended to the parent's, and every other field of the child replaces the parent's. The
+    // client jar stays the parent's unless the child names its own.
+    fn merge(parent: Value, child: Value) -> Value {
+        let (mut parent, child) = match (parent, child) {
+            (Value::Object(parent), Value::Object(child)) => (parent, child),
+            (_, child) => return child,
+        };
+        // A child with `arguments` over a legacy parent would hide the parent's `minecraftArguments`, so
+        // those are carried into an `arguments` object the child's lists are appended to.
+        if child.contains_key("arguments") && !parent.contains_key("arguments") {
+            if let Some(Value::String(minecraft_arguments)) = parent.remove("minecraftArguments") {
+                let game: Vec<Value> = minecraft_arguments.split_whitespace().map(Value::from).collect();
+                let jvm: Vec<Value> = LEGACY_JVM_ARGUMENTS.iter().map(|argument| Value::from(*argument)).collect();
+                parent.insert(String::from("arguments"), serde_json::json!({ "game": game, "jvm": jvm }));
+            }
+        }
+        if !child.contains_key("jar") {
+            let jar = parent.get("jar").or_else(|| parent.get("id")).cloned();
+            if let Some(jar) = jar {
+                parent.insert(String::from("jar"), jar);
+            }
+        }
+        for (key, value) in child {
+            let merged = match (key.as_str(), parent.remove(&key), value) {
+                ("libraries", Some(Value::Array(parent_libraries)), Value::Array(mut libraries)) => {
+                    libraries.extend(parent_libraries);
+                    Value::Array(libraries)
+                }
+                ("arguments", Some(Value::Object(mut parent_arguments)), Value::Object(arguments)) => {
+                    for (side, value) in arguments {
+                        let merged = match (parent_arguments.remove(&side), value) {
+                            (Some(Value::Array(mut parent_values)), Value::Array(values)) => {
+                                parent_values.extend(values);
+                                Value::Array(parent_values)
+                            }
+                            (_, value) => value,
+                        };
+                        parent_arguments.insert(side, merged);
+                    }
+                    Value::Object(parent_arguments)
+                }
+                (_, _, value) => value,
+            };
+            parent.insert(key, merged);
+        }
+        Value::Object(parent)
     }
 
-    pub async fn fetch(version: VersionManifestVersion) -> Result<(), reqwest::Error> {
-        let path: PathBuf = common::join_directories(Vec::from(["meta", "com", "mojang", "minecraft", &version.id, &*format!("{}.json", &version.id)])).unwrap();
-        match common::file_downloader::from_url(&version.url, &path).await {
-            Ok(()) => Ok(()),
-            Err(e) => panic!("{}", e)
+    // Versions before 1.13 only have a `minecraftArguments` string and leave the JVM arguments to the
+    // launcher, so they get the same defaults the vanilla launcher used.
+    pub fn get_jvm_arguments(&self, context: &RuleContext) -> Vec<String> {
+        match &self.arguments {
+            Some(arguments) => VersionArgumentValue::flatten(&arguments.jvm, context),
+            None if self.minecraft_arguments.is_some() => LEGACY_JVM_ARGUMENTS.iter().map(|argument| argument.to_string()).collect(),
+            None => Vec::new(),
         }
     }
+
+    pub fn get_game_arguments(&self, context: &RuleContext) -> Vec<String> {
+        match (&self.arguments, &self.minecraft_arguments) {
+            (Some(arguments), _) => VersionArgumentValue::flatten(&arguments.game, context),
+            (None, Some(minecraft_arguments)) => minecraft_arguments.split_whitespace().map(String::from).collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    // Versions older than 1.17 predate javaVersion and all run on Java 8.
+    pub fn get_java_major_version(&self) -> u32 {
+        self.java_version.as_ref().map(|java_version| java_version.major_version).unwrap_or(8)
+    }
+
+    pub fn client_path(&self, paths: &LauncherPaths) -> PathBuf {
+        let jar = self.jar.as_deref().unwrap_or(&self.id);
+        paths.join(Vec::from(["libraries", "com", "mojang", "minecraft", jar, "client", &format!("{}.jar", jar)]))
+    }
+
+    // The jar that goes on the classpath. Forge and NeoForge 1.17+ keep `${version_name}.jar` out of
+    // their module layer with `-DignoreList`, so a version that runs another version's client jar
+    // gets a copy named after itself next to its JSON.
+    pub fn game_jar_path(&self, paths: &LauncherPaths) -> PathBuf {
+        match &self.jar {
+            Some(jar) if !jar.eq(&self.id) => paths.join(Vec::from(["meta", "versions", &self.id, &format!("{}.jar", self.id)])),
+            _ => self.client_path(paths),
+        }
+    }
+
+    pub fn server_path(&self, paths: &LauncherPaths) -> PathBuf {
+        let jar = self.jar.as_deref().unwrap_or(&self.id);
+        paths.join(Vec::from(["libraries", "com", "mojang", "minecraft", jar, "server", &format!("{}.jar", jar)]))
+    }
+
+    pub async fn fetch(paths: &LauncherPaths, scheduler: &DownloadScheduler, version: VersionManifestVersion) -> Result<(), LauncherError> {
+        let path: PathBuf = Version::vanilla_path(paths, &version.id);
+        scheduler.download(DownloadTask::new(&version.url, path)).await
+    }
+}
+
+impl VersionArgumentValue {
+    pub fn values(&self, context: &RuleContext) -> Vec<String> {
+        match self {
+            VersionArgumentValue::Plain(value) => Vec::from([value.to_owned()]),
+            VersionArgumentValue::Conditional { rules, value } => {
+                if !rule::is_allowed(Some(rules), context) {
+                    return Vec::new();
+                }
+                match value {
+                    VersionArgumentValues::Single(value) => Vec::from([value.to_owned()]),
+                    VersionArgumentValues::Many(values) => values.to_owned(),
+                }
+            }
+        }
+    }
+
+    pub fn flatten(arguments: &[VersionArgumentValue], context: &RuleContext) -> Vec<String> {
+        arguments.iter().flat_map(|argument| argument.values(context)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minecraft::template::{Placeholder, TemplateVariables};
+
+    fn vanilla() -> Value {
+        serde_json::from_str(r#"{
+            "id": "1.16.5",
+            "arguments": {"game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"]},
+            "assetIndex": {"id": "1.16", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "assets": "1.16",
+            "downloads": {},
+            "libraries": [{"name": "com.mojang:brigadier:1.0.17"}, {"name": "org.ow2.asm:asm:7.0"}],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2021-01-14T16:05:32+00:00",
+            "time": "2021-01-14T16:05:32+00:00",
+            "type": "release"
+        }"#).unwrap()
+    }
+
+    fn fabric() -> Value {
+        serde_json::from_str(r#"{
+            "id": "fabric-loader-0.11.3-1.16.5",
+            "inheritsFrom": "1.16.5",
+            "arguments": {"game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]},
+            "libraries": [{"name": "net.fabricmc:fabric-loader:0.11.3", "url": "https://maven.fabricmc.net/"}, {"name": "org.ow2.asm:asm:9.1", "url": "https://maven.fabricmc.net/"}],
+            "mainClass": "net.fabricmc.loader.launch.knot.KnotClient",
+            "releaseTime": "2021-03-18T15:39:27+0000",
+            "time": "2021-03-18T15:39:27+0000",
+            "type": "release"
+        }"#).unwrap()
+    }
+
+    fn library_names(version: &Version) -> Vec<&str> {
+        version.libraries.iter().map(|library| library.name.as_str()).collect()
+    }
+
+    #[test]
+    fn child_libraries_come_first() {
+        let version = Version::merge_chain(Vec::from([fabric(), vanilla()])).unwrap();
+        assert_eq!(library_names(&version), Vec::from(["net.fabricmc:fabric-loader:0.11.3", "org.ow2.asm:asm:9.1", "com.mojang:brigadier:1.0.17", "org.ow2.asm:asm:7.0"]));
+    }
+
+    #[test]
+    fn arguments_are_appended_to_the_parent() {
+        let version = Version::merge_chain(Vec::from([fabric(), vanilla()])).unwrap();
+        let context = RuleContext::new("linux", "amd64", "");
+        assert_eq!(version.get_game_arguments(&context), Vec::from(["--username", "${auth_player_name}"]));
+        assert_eq!(version.get_jvm_arguments(&context), Vec::from(["-cp", "${classpath}", "-DFabricMcEmu= net.minecraft.client.main.Main "]));
+    }
+
+    #[test]
+    fn child_fields_replace_the_parent() {
+        let version = Version::merge_chain(Vec::from([fabric(), vanilla()])).unwrap();
+        assert_eq!(version.id, "fabric-loader-0.11.3-1.16.5");
+        assert_eq!(version.main_class, "net.fabricmc.loader.launch.knot.KnotClient");
+        assert_eq!(version.assets, "1.16");
+        assert_eq!(version.minimum_launcher_version, 21);
+    }
+
+    #[test]
+    fn jar_is_carried_over_from_the_root() {
+        let mut grandchild = fabric();
+        grandchild["id"] = Value::from("modpack");
+        grandchild["inheritsFrom"] = Value::from("fabric-loader-0.11.3-1.16.5");
+        let version = Version::merge_chain(Vec::from([grandchild, fabric(), vanilla()])).unwrap();
+        assert_eq!(version.jar.as_deref(), Some("1.16.5"));
+
+        let mut named = fabric();
+        named["jar"] = Value::from("1.16.5-patched");
+        let version = Version::merge_chain(Vec::from([named, vanilla()])).unwrap();
+        assert_eq!(version.jar.as_deref(), Some("1.16.5-patched"));
+    }
+
+    #[test]
+    fn a_version_without_parent_keeps_its_own_jar() {
+        let version = Version::merge_chain(Vec::from([vanilla()])).unwrap();
+        assert_eq!(version.jar, None);
+        assert_eq!(version.id, "1.16.5");
+    }
+
+    #[test]
+    fn inheritance_cycles_are_errors() {
+        let fabric = fabric();
+        assert_eq!(Version::parent_of(&[], "fabric-loader-0.11.3-1.16.5", &fabric).unwrap().as_deref(), Some("1.16.5"));
+
+        let mut looping = vanilla();
+        looping["inheritsFrom"] = Value::from("fabric-loader-0.11.3-1.16.5");
+        assert!(Version::parent_of(&[fabric], "1.16.5", &looping).is_err());
+
+        let mut own = vanilla();
+        own["inheritsFrom"] = Value::from("1.16.5");
+        assert!(Version::parent_of(&[], "1.16.5", &own).is_err());
+    }
+
+    #[test]
+    fn legacy_versions_split_minecraft_arguments() {
+        let mut legacy = vanilla();
+        legacy.as_object_mut().unwrap().remove("arguments");
+        legacy["minecraftArguments"] = Value::from("--username ${auth_player_name} --version ${version_name}");
+        let version = Version::merge_chain(Vec::from([legacy])).unwrap();
+        let context = RuleContext::new("linux", "amd64", "");
+        assert_eq!(version.get_game_arguments(&context), Vec::from(["--username", "${auth_player_name}", "--version", "${version_name}"]));
+        assert_eq!(version.get_jvm_arguments(&context), Vec::from(LEGACY_JVM_ARGUMENTS));
+    }
+
+    #[test]
+    fn child_arguments_keep_the_legacy_parent_arguments() {
+        let mut legacy = vanilla();
+        legacy.as_object_mut().unwrap().remove("arguments");
+        legacy["minecraftArguments"] = Value::from("--username ${auth_player_name} --version ${version_name}");
+        let mut child = fabric();
+        child["arguments"] = serde_json::from_str(r#"{"game": ["--tweakClass", "optifine.OptiFineTweaker"], "jvm": ["-Dfabric.side=client"]}"#).unwrap();
+        let version = Version::merge_chain(Vec::from([child, legacy])).unwrap();
+        let context = RuleContext::new("linux", "amd64", "");
+        assert_eq!(version.minecraft_arguments, None);
+        assert_eq!(version.get_game_arguments(&context), Vec::from(["--username", "${auth_player_name}", "--version", "${version_name}", "--tweakClass", "optifine.OptiFineTweaker"]));
+        assert_eq!(version.get_jvm_arguments(&context), Vec::from(["-Djava.library.path=${natives_directory}", "-cp", "${classpath}", "-Dfabric.side=client"]));
+    }
+
+    #[test]
+    fn conditional_arguments_follow_their_rules() {
+        let mut conditional = vanilla();
+        conditional["arguments"]["game"] = serde_json::from_str(r#"["--demo", {"rules": [{"action": "allow", "features": {"has_custom_resolution": true}}], "value": ["--width", "${resolution_width}"]}]"#).unwrap();
+        let version = Version::merge_chain(Vec::from([conditional])).unwrap();
+        let context = RuleContext::new("linux", "amd64", "");
+        assert_eq!(version.get_game_arguments(&context), Vec::from(["--demo"]));
+        let context = context.with_feature(rule::FEATURE_CUSTOM_RESOLUTION, true);
+        assert_eq!(version.get_game_arguments(&context), Vec::from(["--demo", "--width", "${resolution_width}"]));
+    }
+
+    #[test]
+    fn forge_classpath_names_the_game_jar_after_the_version() {
+        let paths = crate::common::test_support::temp_paths("version");
+        let mut vanilla = vanilla();
+        vanilla["id"] = Value::from("1.17.1");
+        let forge: Value = serde_json::from_str(r#"{
+            "id": "1.17.1-forge-37.1.1",
+            "inheritsFrom": "1.17.1",
+            "arguments": {"game": ["--launchTarget", "forgeclient"], "jvm": ["-DignoreList=bootstraplauncher,securejarhandler,asm-commons,asm-util,asm-analysis,asm-tree,asm,client-extra,fmlcore,javafmllanguage,mclanguage,forge-,${version_name}.jar"]},
+            "libraries": [],
+            "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+            "releaseTime": "2021-12-01T00:00:00+00:00",
+            "time": "2021-12-01T00:00:00+00:00",
+            "type": "release"
+        }"#).unwrap();
+        let vanilla_version = Version::merge_chain(Vec::from([vanilla.to_owned()])).unwrap();
+        let client = vanilla_version.client_path(&paths);
+        std::fs::create_dir_all(client.parent().unwrap()).unwrap();
+        std::fs::write(&client, b"client").unwrap();
+        assert_eq!(vanilla_version.get_classpath(&paths).unwrap().last(), Some(&client));
+
+        let version = Version::merge_chain(Vec::from([forge, vanilla])).unwrap();
+        let game_jar = version.get_classpath(&paths).unwrap().last().unwrap().to_owned();
+        assert_eq!(game_jar.file_name().unwrap(), "1.17.1-forge-37.1.1.jar");
+        assert_eq!(std::fs::read(&game_jar).unwrap(), b"client");
+
+        let variables = TemplateVariables::new().with(Placeholder::VersionName, &version.id);
+        let jvm_arguments = version.get_jvm_arguments(&RuleContext::new("linux", "amd64", ""));
+        let ignore_list = jvm_arguments.iter().find(|argument| argument.starts_with("-DignoreList=")).unwrap();
+        let ignore_list = variables.render(ignore_list).unwrap();
+        assert!(ignore_list["-DignoreList=".len()..].split(',').any(|ignored| game_jar.file_name().unwrap().eq(ignored)));
+        std::fs::remove_dir_all(paths.root()).unwrap();
+    }
 }
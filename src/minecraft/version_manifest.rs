@@ -4,7 +4,9 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
-use crate::common;
+use crate::common::download_scheduler::{DownloadScheduler, DownloadTask};
+use crate::common::error::LauncherError;
+use crate::common::launcher_paths::LauncherPaths;
 
 const MINECRAFT_VERSION_MANIFEST: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
 
@@ -31,23 +33,19 @@ pub struct VersionManifestVersion {
 }
 
 impl VersionManifest{
-    pub async fn get(refresh: bool) -> Result<VersionManifest, std::io::Error> {
-        let path: PathBuf = common::join_directories(Vec::from(["meta", "com", "mojang", "minecraft", "version_manifest.json"])).unwrap();
-        if path.exists() && !refresh {
-            return VersionManifest::read(&path);
-        } else {
-            match common::file_downloader::from_url(MINECRAFT_VERSION_MANIFEST, &path).await {
-                Ok(()) => return VersionManifest::read(&path),
-                Err(e) => panic!("{}", e)
-            }
+    pub async fn get(paths: &LauncherPaths, scheduler: &DownloadScheduler, refresh: bool) -> Result<VersionManifest, LauncherError> {
+        let path: PathBuf = paths.join(Vec::from(["meta", "com", "mojang", "minecraft", "version_manifest.json"]));
+        if !path.exists() || refresh {
+            scheduler.download(DownloadTask::new(MINECRAFT_VERSION_MANIFEST, path.to_owned())).await?;
         }
+        VersionManifest::read(&path)
     }
 
-    pub fn read(path: &PathBuf) -> Result<VersionManifest, std::io::Error> {
+    pub fn read(path: &PathBuf) -> Result<VersionManifest, LauncherError> {
         let mut file = File::open(path)?;
         let mut data = String::new();
-        file.read_to_string(&mut data).expect("Unable to read file");
-        let version: VersionManifest = serde_json::from_str(&data).expect("JSON was not well-formatted");
+        file.read_to_string(&mut data)?;
+        let version: VersionManifest = serde_json::from_str(&data)?;
         Ok(version)
     }
 }
@@ -1,6 +1,8 @@
 use reqwest::StatusCode;
 use serde::Deserialize;
 
+use crate::common::error::LauncherError;
+
 pub const MOJANG_API: &str = "https://authserver.mojang.com";
 
 #[derive(Debug, Deserialize)]
@@ -52,7 +54,7 @@ pub struct AuthenticateResponseUser {
     pub properties: Option<Vec<UserProperty>>,
 }
 
-pub async fn authenticate(email: &str, password: &str, client_token: &str) -> Result<Option<AuthenticateResponse>, reqwest::Error> {
+pub async fn authenticate(email: &str, password: &str, client_token: &str) -> Result<AuthenticateResponse, LauncherError> {
     let client = reqwest::Client::new();
     let request_url = format!("{api}/{path}", api = MOJANG_API, path = "authenticate");
 
@@ -77,7 +79,10 @@ pub async fn authenticate(email: &str, password: &str, client_token: &str) -> Re
         .json()
         .await?;
 
-    Ok(Some(value))
+    match &value.error {
+        Some(error) => Err(LauncherError::Auth(value.error_message.to_owned().unwrap_or_else(|| error.to_owned()))),
+        None => Ok(value)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -103,7 +108,7 @@ pub struct RefreshResponseUser {
     pub properties: Option<Vec<UserProperty>>,
 }
 
-pub async fn refresh(access_token: &str, client_token: &str, selected_profile: &RefreshResponseProfile) -> Result<Option<RefreshResponse>, reqwest::Error> {
+pub async fn refresh(access_token: &str, client_token: &str, selected_profile: &RefreshResponseProfile) -> Result<RefreshResponse, LauncherError> {
     let client = reqwest::Client::new();
     let request_url = format!("{api}/{path}", api = MOJANG_API, path = "refresh");
 
@@ -125,10 +130,13 @@ pub async fn refresh(access_token: &str, client_token: &str, selected_profile: &
 
     let value: RefreshResponse = response.json().await?;
 
-    Ok(Some(value))
+    match &value.error {
+        Some(error) => Err(LauncherError::Auth(value.error_message.to_owned().unwrap_or_else(|| error.to_owned()))),
+        None => Ok(value)
+    }
 }
 
-pub async fn validate(access_token: &str, client_token: &str) -> Result<bool, reqwest::Error> {
+pub async fn validate(access_token: &str, client_token: &str) -> Result<bool, LauncherError> {
     let client = reqwest::Client::new();
     let request_url = format!("{api}/{path}", api = MOJANG_API, path = "validate");
 
@@ -150,7 +158,7 @@ pub async fn validate(access_token: &str, client_token: &str) -> Result<bool, re
     }
 }
 
-pub async fn sign_out(email: &str, password: &str) -> Result<bool, reqwest::Error> {
+pub async fn sign_out(email: &str, password: &str) -> Result<bool, LauncherError> {
     let client = reqwest::Client::new();
     let request_url = format!("{api}/{path}", api = MOJANG_API, path = "signout");
 
@@ -172,7 +180,7 @@ pub async fn sign_out(email: &str, password: &str) -> Result<bool, reqwest::Erro
     }
 }
 
-pub async fn invalidate(access_token: &str, client_token: &str) -> Result<bool, reqwest::Error> {
+pub async fn invalidate(access_token: &str, client_token: &str) -> Result<bool, LauncherError> {
     let client = reqwest::Client::new();
     let request_url = format!("{api}/{path}", api = MOJANG_API, path = "invalidate");
 
@@ -0,0 +1,556 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use duck_launcher::common::download_scheduler::DownloadScheduler;
+use duck_launcher::common::error::LauncherError;
+use duck_launcher::common::file_downloader::VerifyMode;
+use duck_launcher::common::launcher_paths::LauncherPaths;
+use duck_launcher::minecraft::account::{Account, Accounts};
+use duck_launcher::minecraft::dependency;
+use duck_launcher::minecraft::dependency::LibrariesMetadata;
+use duck_launcher::minecraft::fabric::FabricMeta;
+use duck_launcher::minecraft::forge::ForgeMeta;
+use duck_launcher::minecraft::neoforge::NeoForgeMeta;
+use duck_launcher::minecraft;
+use duck_launcher::minecraft::instance::{Instance, InstanceFlavor, InstanceType};
+use duck_launcher::minecraft::java;
+use duck_launcher::minecraft::process::{GameConsole, GameState};
+use duck_launcher::minecraft::java::{JavaRuntime, JavaRuntimes};
+use duck_launcher::minecraft::runtime::RuntimeMeta;
+use duck_launcher::minecraft::version::Version;
+use duck_launcher::minecraft::version_manifest::VersionManifest;
+use duck_launcher::minecraft::yggdrasil;
+
+use crate::ui::cui::output::{AccountOutput, AccountsOutput, GarbageOutput, InstanceOutput, InstancesOutput, JavaRuntimesOutput, LatestOutput, LibrariesOutput, StatusOutput, VersionOutput, VersionsOutput, FORMAT_VERSION};
+use crate::ui::cui::output;
+
+const PASSWORD_ENV: &str = "DUCK_LAUNCHER_PASSWORD";
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "duck-launcher", about = "A Minecraft launcher")]
+pub struct Cli {
+    /// Data directory, overrides DUCK_LAUNCHER_HOME and the platform default
+    #[structopt(long, global = true, parse(from_os_str))]
+    pub data_dir: Option<PathBuf>,
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[structopt(short, long, global = true, parse(from_occurrences))]
+    pub verbose: u8,
+    /// Verify downloaded files by size only instead of SHA-1
+    #[structopt(long, global = true)]
+    pub fast_verify: bool,
+    /// Print machine-readable JSON to stdout, see README.md for the schemas
+    #[structopt(long, global = true)]
+    pub json: bool,
+    /// Maximum number of concurrent downloads
+    #[structopt(long, global = true, default_value = "16")]
+    pub max_downloads: usize,
+    #[structopt(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// List and install game versions
+    Versions(VersionsCommand),
+    /// Create, list, launch and delete instances
+    Instances(InstancesCommand),
+    /// Add, list and remove Mojang accounts
+    Accounts(AccountsCommand),
+    /// Verify and repair game assets
+    Assets(AssetsCommand),
+    /// Manage the shared libraries directory
+    Libraries(LibrariesCommand),
+    /// List and register Java runtimes
+    Java(JavaCommand),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum VersionsCommand {
+    /// List available versions, releases only unless --all is given
+    List {
+        #[structopt(long)]
+        all: bool,
+    },
+    /// Download a version with its libraries, natives and assets
+    Install {
+        id: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum InstancesCommand {
+    /// Install a version and create an instance for it
+    Create {
+        name: String,
+        /// Minecraft version the instance plays
+        #[structopt(long)]
+        version: String,
+        /// vanilla, fabric, forge, neoforge or quilt
+        #[structopt(long, default_value = "vanilla")]
+        flavor: InstanceFlavor,
+        /// client or server
+        #[structopt(long = "type", default_value = "client")]
+        r#type: InstanceType,
+        /// Mod loader version, defaults to the latest stable one
+        #[structopt(long)]
+        loader_version: Option<String>,
+        /// Username of the account to launch with by default
+        #[structopt(long)]
+        account: Option<String>,
+        /// Extra JVM arguments, separated by spaces
+        #[structopt(long, allow_hyphen_values = true)]
+        jvm_arguments: Option<String>,
+        /// Java home or executable, picked from the discovered runtimes when omitted
+        #[structopt(long, parse(from_os_str))]
+        java: Option<PathBuf>,
+        #[structopt(long = "tag")]
+        tags: Vec<String>,
+        /// server.properties entry of a server instance, as key=value
+        #[structopt(long = "property")]
+        properties: Vec<String>,
+    },
+    /// List instances
+    List,
+    /// Launch an instance
+    Launch {
+        name: String,
+        /// Username of the account to play with, defaults to the instance account or the only stored account
+        #[structopt(long)]
+        account: Option<String>,
+        /// Accept the Minecraft EULA for a server instance instead of being asked
+        #[structopt(long)]
+        accept_eula: bool,
+    },
+    /// Delete an instance and its game directory
+    Delete {
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AccountsCommand {
+    /// Authenticate and store an account
+    ///
+    /// The password is read from DUCK_LAUNCHER_PASSWORD, or prompted on stdin when unset
+    Add {
+        email: String,
+    },
+    /// List stored accounts
+    List,
+    /// Invalidate and remove a stored account
+    Remove {
+        username: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AssetsCommand {
+    /// Verify the assets of a version and download missing or corrupt objects
+    Verify {
+        version: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum LibrariesCommand {
+    /// List the libraries, clients and servers recorded in the libraries metadata
+    List,
+    /// Remove libraries no installed version references
+    Gc {
+        /// Only list the files that would be removed
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum JavaCommand {
+    /// List the Java runtimes that were found
+    List,
+    /// Register a Java home or executable
+    Add {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Forget a registered Java runtime
+    Remove {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Download a Java runtime component such as java-runtime-gamma or jre-legacy
+    Install {
+        component: String,
+    },
+}
+
+impl Cli {
+    pub fn log_level(&self) -> log::LevelFilter {
+        match self.verbose {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+
+    fn scheduler(&self) -> DownloadScheduler {
+        let verify_mode = if self.fast_verify {
+            VerifyMode::Size
+        } else {
+            VerifyMode::Sha1
+        };
+        self.scheduler_with_verify_mode(verify_mode)
+    }
+
+    fn scheduler_with_verify_mode(&self, verify_mode: VerifyMode) -> DownloadScheduler {
+        let scheduler = DownloadScheduler::new().with_verify_mode(verify_mode).with_max_concurrent(self.max_downloads);
+        tokio::spawn(super::print_download_progress(scheduler.subscribe()));
+        scheduler
+    }
+}
+
+pub async fn run(cli: Cli) -> Result<(), LauncherError> {
+    let paths = LauncherPaths::resolve(cli.data_dir.to_owned())?;
+    log::debug!("Using data directory {}", paths.root().display());
+    match &cli.command {
+        Command::Versions(command) => versions(&cli, &paths, command).await,
+        Command::Instances(command) => instances(&cli, &paths, command).await,
+        Command::Accounts(command) => accounts(&cli, &paths, command).await,
+        Command::Assets(command) => assets(&cli, &paths, command).await,
+        Command::Libraries(command) => libraries(&cli, &paths, command),
+        Command::Java(command) => java(&cli, &paths, command).await,
+    }
+}
+
+async fn versions(cli: &Cli, paths: &LauncherPaths, command: &VersionsCommand) -> Result<(), LauncherError> {
+    let scheduler = cli.scheduler();
+    match command {
+        VersionsCommand::List { all } => {
+            let manifest = VersionManifest::get(paths, &scheduler, true).await?;
+            let installed = Version::installed(paths)?;
+            let mut versions: Vec<VersionOutput> = Vec::new();
+            for version in manifest.versions {
+                if *all || version.r#type.eq("release") {
+                    versions.push(VersionOutput {
+                        installed: installed.contains(&version.id),
+                        id: version.id,
+                        r#type: version.r#type,
+                        release_time: version.release_time,
+                    });
+                }
+            }
+            if cli.json {
+                output::print_json(&VersionsOutput {
+                    format_version: FORMAT_VERSION,
+                    latest: LatestOutput {
+                        release: manifest.latest.release,
+                        snapshot: manifest.latest.snapshot,
+                    },
+                    versions,
+                });
+            } else {
+                println!("Latest release: {}, latest snapshot: {}", manifest.latest.release, manifest.latest.snapshot);
+                for version in versions {
+                    let marker = if version.installed { "*" } else { " " };
+                    println!("{} {} ({})", marker, version.id, version.r#type);
+                }
+            }
+        }
+        VersionsCommand::Install { id } => {
+            let version = Version::get_version(paths, &scheduler, id).await?;
+            version.install(paths, &scheduler).await?;
+            log::info!("Installed {}", version.id);
+            print_status(cli, "versions install", &version.id);
+        }
+    }
+    Ok(())
+}
+
+async fn instances(cli: &Cli, paths: &LauncherPaths, command: &InstancesCommand) -> Result<(), LauncherError> {
+    match command {
+        InstancesCommand::Create { name, version, flavor, r#type, loader_version, account, jvm_arguments, java, tags, properties } => {
+            if Instance::exists(paths, name) {
+                return Err(LauncherError::InvalidInstance(format!("Instance {} already exists", name)));
+            }
+            let mut instance = Instance::new(name, version, account.as_deref().unwrap_or_default()).with_flavor(*flavor).with_type(*r#type);
+            if let Some(jvm_arguments) = jvm_arguments {
+                instance = instance.with_jvm_arguments(jvm_arguments.to_owned());
+            }
+            if let Some(java) = java {
+                instance = instance.with_java_path(java.to_owned());
+            }
+            for tag in tags {
+                instance.add_tag(tag.to_owned());
+            }
+            for property in properties {
+                match property.split_once('=') {
+                    Some((key, value)) => instance.set_server_property(key.trim().to_string(), value.to_string()),
+                    None => return Err(LauncherError::InvalidInstance(format!("Invalid server property {}, expected key=value", property))),
+                }
+            }
+            let scheduler = cli.scheduler();
+            let loader_version = match (flavor, loader_version) {
+                (_, Some(loader_version)) => Some(loader_version.to_owned()),
+                (InstanceFlavor::FABRIC, None) => Some(FabricMeta::from_env().get_latest_loader_version(&scheduler, version).await?),
+                (InstanceFlavor::FORGE, None) => Some(ForgeMeta::from_env().get_latest_version(&scheduler, version).await?),
+                (InstanceFlavor::NEOFORGE, None) => Some(NeoForgeMeta::from_env().get_latest_version(&scheduler, version).await?),
+                (InstanceFlavor::QUILT, None) => Some(FabricMeta::quilt_from_env().get_latest_loader_version(&scheduler, version).await?),
+                (_, None) => None,
+            };
+            if let Some(loader_version) = loader_version {
+                instance = instance.with_loader_version(loader_version);
+            }
+            instance.install(paths, &scheduler).await?;
+            instance.save(paths)?;
+            log::info!("Created instance {} ({})", name, version);
+            print_status(cli, "instances create", name);
+        }
+        InstancesCommand::List => {
+            let instances = Instance::list(paths)?;
+            if cli.json {
+                output::print_json(&InstancesOutput {
+                    format_version: FORMAT_VERSION,
+                    instances: instances.iter().map(|instance| InstanceOutput::new(paths, instance)).collect(),
+                });
+            } else {
+                for instance in instances {
+                    println!("{} ({}, {})", instance.name(), instance.game_version(), instance.flavor());
+                }
+            }
+        }
+        InstancesCommand::Launch { name, account, accept_eula } => {
+            let instance = Instance::load(paths, name)?;
+            // The tree was verified in full when the instance was created, so a launch only checks sizes to
+            // repair missing or truncated files instead of hashing every asset and library again.
+            let scheduler = cli.scheduler_with_verify_mode(VerifyMode::Size);
+            let game = match instance.r#type() {
+                InstanceType::CLIENT => instance.launch(paths, &scheduler, account.as_deref()).await?,
+                InstanceType::SERVER => {
+                    let mut accept_eula = *accept_eula;
+                    if !accept_eula && !cli.json && !minecraft::is_eula_accepted(&instance.game_directory(paths)) {
+                        let answer = read_line(&format!("Do you accept the Minecraft EULA ({})? [y/N] ", minecraft::EULA_URL))?;
+                        accept_eula = answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes");
+                    }
+                    let server = instance.launch_server(paths, &scheduler, accept_eula).await?;
+                    if let Some(console) = server.console() {
+                        forward_stdin(console);
+                    }
+                    server
+                }
+            };
+            // Keeps stdout for the status document when printing JSON.
+            let json = cli.json;
+            let mut stdout = game.stdout();
+            let mut stderr = game.stderr();
+            let printers = Vec::from([
+                tokio::spawn(async move {
+                    while let Some(line) = stdout.recv().await {
+                        if json { eprintln!("{}", line) } else { println!("{}", line) }
+                    }
+                }),
+                tokio::spawn(async move {
+                    while let Some(line) = stderr.recv().await {
+                        eprintln!("{}", line);
+                    }
+                }),
+            ]);
+            let state = game.wait().await;
+            for printer in printers {
+                let _ = printer.await;
+            }
+            if let GameState::Exited { code: Some(code) } = state {
+                if code != 0 {
+                    return Err(LauncherError::Launch(format!("{} exited with code {}", name, code)));
+                }
+            }
+            print_status(cli, "instances launch", name);
+        }
+        InstancesCommand::Delete { name } => {
+            Instance::delete(paths, name)?;
+            log::info!("Deleted instance {}", name);
+            print_status(cli, "instances delete", name);
+        }
+    }
+    Ok(())
+}
+
+async fn accounts(cli: &Cli, paths: &LauncherPaths, command: &AccountsCommand) -> Result<(), LauncherError> {
+    let mut accounts = Accounts::load(paths)?;
+    match command {
+        AccountsCommand::Add { email } => {
+            // Never taken as an argument, where it would show up in the process list and shell history.
+            let password = match std::env::var(PASSWORD_ENV) {
+                Ok(password) if !password.is_empty() => password,
+                _ => read_line("Password: ")?,
+            };
+            let response = yggdrasil::authenticate(email, &password, "").await?;
+            let account = Account::from_authenticate_response(email, response)?;
+            log::info!("Added account {}", email);
+            accounts.add(account);
+            accounts.save(paths)?;
+            print_status(cli, "accounts add", email);
+        }
+        AccountsCommand::List => {
+            if cli.json {
+                output::print_json(&AccountsOutput {
+                    format_version: FORMAT_VERSION,
+                    accounts: accounts.accounts.iter().map(AccountOutput::from).collect(),
+                });
+            } else {
+                for account in &accounts.accounts {
+                    let profile = account.get_active_profile().map(|profile| profile.name.as_str()).unwrap_or_default();
+                    println!("{} ({})", account.username, profile);
+                }
+            }
+        }
+        AccountsCommand::Remove { username } => {
+            let account = match accounts.remove(username) {
+                Some(account) => account,
+                None => return Err(LauncherError::Auth(format!("Account {} not found", username))),
+            };
+            if let Err(e) = yggdrasil::invalidate(&account.access_token, &account.client_token).await {
+                log::warn!("Unable to invalidate the access token of {}: {}", username, e);
+            }
+            accounts.save(paths)?;
+            log::info!("Removed account {}", username);
+            print_status(cli, "accounts remove", username);
+        }
+    }
+    Ok(())
+}
+
+async fn assets(cli: &Cli, paths: &LauncherPaths, command: &AssetsCommand) -> Result<(), LauncherError> {
+    match command {
+        AssetsCommand::Verify { version } => {
+            let scheduler = cli.scheduler();
+            let version = Version::get_version(paths, &scheduler, version).await?;
+            version.verify_assets(paths, &scheduler).await?;
+            let report = scheduler.run().await;
+            if !report.is_success() {
+                return Err(LauncherError::Download(report));
+            }
+            print_status(cli, "assets verify", &version.id);
+        }
+    }
+    Ok(())
+}
+
+fn libraries(cli: &Cli, paths: &LauncherPaths, command: &LibrariesCommand) -> Result<(), LauncherError> {
+    match command {
+        LibrariesCommand::List => {
+            let metadata = LibrariesMetadata::new(paths)?;
+            if cli.json {
+                output::print_json(&LibrariesOutput {
+                    format_version: FORMAT_VERSION,
+                    libraries: &metadata.libraries,
+                    clients: &metadata.clients,
+                    servers: &metadata.servers,
+                });
+            } else {
+                for library in &metadata.libraries {
+                    println!("{}", library.name);
+                }
+                for client in &metadata.clients {
+                    println!("{} (client)", client.name);
+                }
+                for server in &metadata.servers {
+                    println!("{} (server)", server.name);
+                }
+            }
+        }
+        LibrariesCommand::Gc { dry_run } => {
+            let mut versions: Vec<Version> = Vec::new();
+            for id in Version::installed(paths)? {
+                versions.push(Version::read_installed(paths, &id)?);
+            }
+            let files = dependency::collect_garbage(paths, &versions, *dry_run)?;
+            if cli.json {
+                output::print_json(&GarbageOutput {
+                    format_version: FORMAT_VERSION,
+                    dry_run: *dry_run,
+                    files,
+                });
+            } else {
+                for path in files {
+                    println!("{}", path.display());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn java(cli: &Cli, paths: &LauncherPaths, command: &JavaCommand) -> Result<(), LauncherError> {
+    match command {
+        JavaCommand::List => {
+            let runtimes = java::discover(paths)?;
+            if cli.json {
+                output::print_json(&JavaRuntimesOutput {
+                    format_version: FORMAT_VERSION,
+                    runtimes: &runtimes,
+                });
+            } else {
+                for runtime in &runtimes {
+                    println!("{} ({}, {})", runtime.path.display(), runtime.version, runtime.arch);
+                }
+            }
+        }
+        JavaCommand::Add { path } => {
+            let runtime = JavaRuntime::probe(&java::java_executable(path))?;
+            let mut runtimes = JavaRuntimes::load(paths)?;
+            runtimes.add(path.to_owned());
+            runtimes.save(paths)?;
+            log::info!("Registered Java {} ({})", runtime.version, runtime.path.display());
+            print_status(cli, "java add", &path.to_string_lossy());
+        }
+        JavaCommand::Remove { path } => {
+            let mut runtimes = JavaRuntimes::load(paths)?;
+            if runtimes.remove(path).is_none() {
+                return Err(LauncherError::Launch(format!("Java runtime {} is not registered", path.display())));
+            }
+            runtimes.save(paths)?;
+            print_status(cli, "java remove", &path.to_string_lossy());
+        }
+        JavaCommand::Install { component } => {
+            let java = RuntimeMeta::from_env().install(paths, &cli.scheduler(), component).await?;
+            log::info!("Java runtime {} is at {}", component, java.display());
+            print_status(cli, "java install", component);
+        }
+    }
+    Ok(())
+}
+
+fn print_status(cli: &Cli, command: &str, target: &str) {
+    if cli.json {
+        output::print_json(&StatusOutput::ok(command, target));
+    }
+}
+
+// Passes lines typed on stdin to the server console and stops the server cleanly once stdin is closed.
+fn forward_stdin(console: GameConsole) {
+    let runtime = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if runtime.block_on(console.send_line(&line)).is_err() {
+                return;
+            }
+        }
+        let _ = runtime.block_on(console.send_line("stop"));
+    });
+}
+
+fn read_line(prompt: &str) -> Result<String, LauncherError> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
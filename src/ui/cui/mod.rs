@@ -1,11 +1,60 @@
-fn read_line() -> Option<u8>{
-    let mut line = String::new();
-    std::io::stdin().read_line(&mut line).expect("Something went wrong!");
-    match line.parse(){
-        Ok(val) => Some(val),
-        Err(e) => {
-            println!("Invalid Input! Try Again");
-            read_line()
+pub mod cli;
+pub mod output;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use duck_launcher::common::download_progress::DownloadEvent;
+
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+pub async fn print_download_progress(mut receiver: UnboundedReceiver<DownloadEvent>) {
+    let mut files: usize = 0;
+    let mut finished: usize = 0;
+    let mut total_bytes: u64 = 0;
+    let mut received: HashMap<String, u64> = HashMap::new();
+    while let Some(event) = receiver.recv().await {
+        match event {
+            DownloadEvent::VerifyStarted { component, .. } => eprintln!("Verifying {}", component),
+            DownloadEvent::VerifyFinished { component, queued } => eprintln!("{} {} files queued", component, queued),
+            DownloadEvent::QueueStarted { files: queue_files, total_bytes: queue_bytes } => {
+                files = queue_files;
+                finished = 0;
+                total_bytes = queue_bytes;
+                received.clear();
+            }
+            DownloadEvent::BytesReceived { url, received: bytes, .. } => {
+                received.insert(url, bytes);
+            }
+            DownloadEvent::TaskFinished { .. } => finished += 1,
+            DownloadEvent::TaskFailed { url, error, .. } => {
+                finished += 1;
+                eprintln!("\nFailed {}: {}", url, error);
+            }
+            DownloadEvent::QueueFinished { .. } => {
+                print_progress_bar(finished, files, received.values().sum(), total_bytes);
+                eprintln!();
+                continue;
+            }
+            _ => continue,
+        }
+        if files > 0 {
+            print_progress_bar(finished, files, received.values().sum(), total_bytes);
         }
     }
-}
\ No newline at end of file
+}
+
+fn print_progress_bar(finished: usize, files: usize, received: u64, total_bytes: u64) {
+    let ratio = if total_bytes > 0 {
+        (received as f64 / total_bytes as f64).min(1.0)
+    } else if files > 0 {
+        finished as f64 / files as f64
+    } else {
+        1.0
+    };
+    let filled = (ratio * PROGRESS_BAR_WIDTH as f64) as usize;
+    eprint!("\r[{}{}] {}/{} files, {:.1}/{:.1} MiB", "#".repeat(filled), " ".repeat(PROGRESS_BAR_WIDTH - filled), finished, files, received as f64 / 1048576.0, total_bytes as f64 / 1048576.0);
+    std::io::stderr().flush().unwrap_or_default();
+}
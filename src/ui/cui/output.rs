@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use duck_launcher::common::launcher_paths::LauncherPaths;
+use duck_launcher::minecraft::account::Account;
+use duck_launcher::minecraft::dependency::LibrariesMetadataDependency;
+use duck_launcher::minecraft::instance::{Instance, InstanceFlavor, InstanceType};
+use duck_launcher::minecraft::java::JavaRuntime;
+
+// Bump whenever a field is removed or changes meaning; adding fields is not a breaking change.
+// The schemas are documented in README.md.
+pub const FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct VersionsOutput {
+    pub format_version: u8,
+    pub latest: LatestOutput,
+    pub versions: Vec<VersionOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatestOutput {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionOutput {
+    pub id: String,
+    pub r#type: String,
+    pub release_time: String,
+    pub installed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstancesOutput {
+    pub format_version: u8,
+    pub instances: Vec<InstanceOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstanceOutput {
+    pub name: String,
+    pub path: PathBuf,
+    pub game_version: String,
+    pub r#type: InstanceType,
+    pub flavor: InstanceFlavor,
+    pub loader_version: Option<String>,
+    pub java_path: Option<PathBuf>,
+    pub account: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountsOutput {
+    pub format_version: u8,
+    pub accounts: Vec<AccountOutput>,
+}
+
+// Never includes the access or client token.
+#[derive(Debug, Serialize)]
+pub struct AccountOutput {
+    pub username: String,
+    pub user_id: String,
+    pub profile_id: Option<String>,
+    pub profile_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibrariesOutput<'a> {
+    pub format_version: u8,
+    pub libraries: &'a Vec<LibrariesMetadataDependency>,
+    pub clients: &'a Vec<LibrariesMetadataDependency>,
+    pub servers: &'a Vec<LibrariesMetadataDependency>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GarbageOutput {
+    pub format_version: u8,
+    pub dry_run: bool,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JavaRuntimesOutput<'a> {
+    pub format_version: u8,
+    pub runtimes: &'a Vec<JavaRuntime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusOutput {
+    pub format_version: u8,
+    pub command: String,
+    pub target: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorOutput {
+    pub format_version: u8,
+    pub error: String,
+}
+
+impl From<&Account> for AccountOutput {
+    fn from(account: &Account) -> Self {
+        let profile = account.get_active_profile();
+        AccountOutput {
+            username: account.username.to_owned(),
+            user_id: account.user.id.to_owned(),
+            profile_id: profile.map(|profile| profile.id.to_owned()),
+            profile_name: profile.map(|profile| profile.name.to_owned()),
+        }
+    }
+}
+
+impl InstanceOutput {
+    pub fn new(paths: &LauncherPaths, instance: &Instance) -> InstanceOutput {
+        InstanceOutput {
+            name: instance.name().to_string(),
+            path: instance.directory(paths),
+            game_version: instance.game_version().to_string(),
+            r#type: *instance.r#type(),
+            flavor: *instance.flavor(),
+            loader_version: instance.loader_version().cloned(),
+            java_path: instance.java_path().cloned(),
+            account: Some(instance.selected_account().to_string()).filter(|account| !account.is_empty()),
+            tags: instance.tags().cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl StatusOutput {
+    pub fn ok(command: &str, target: &str) -> StatusOutput {
+        StatusOutput {
+            format_version: FORMAT_VERSION,
+            command: command.to_string(),
+            target: target.to_string(),
+            success: true,
+        }
+    }
+}
+
+impl ErrorOutput {
+    pub fn new(error: &str) -> ErrorOutput {
+        ErrorOutput {
+            format_version: FORMAT_VERSION,
+            error: error.to_string(),
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => log::error!("Unable to serialize output: {}", e),
+    }
+}
@@ -1 +1 @@
-pub mod cui;
\ No newline at end of file
+pub mod cui;